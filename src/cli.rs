@@ -0,0 +1,237 @@
+use interpreter::evaluator_utils::object::Object;
+use interpreter::formatter_utils::formatter::format_source;
+use interpreter::lexer_utils::lexer::Lexer;
+use interpreter::parser_utils::ast::Program;
+use interpreter::parser_utils::dump::{source_to_json, to_sexp};
+use interpreter::parser_utils::parser::Parser;
+use interpreter::repl_utils::{repl, transcript};
+use interpreter::{CrabError, Interpreter};
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "usage: interpreter [command] [args]
+
+commands:
+  run <file>       execute a script (`-` reads standard input)
+  run -e <code>    execute an inline snippet
+  repl [--rc <file>]
+                   start the interactive prompt (the default), evaluating
+                   <file>, $CRABRC or ~/.crabrc first
+  check <file>...  parse files and report diagnostics without running them
+  tokens <file>    print the lexer output
+  ast [--json|--sexp] <file>
+                   print the syntax tree as S-expressions (the default) or
+                   as JSON with source spans
+  fmt <file>       print the source in canonical format
+  fmt --check <file>...
+                   exit with an error if any file isn't formatted
+  test [--bless] <file>...
+                   replay .crbt transcripts and report output that differs,
+                   or with --bless rewrite them with the actual output
+
+`interpreter <file>` and `interpreter -e <code>` are shorthands for `run`.";
+
+pub fn dispatch(args: &[String]) {
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    match args.as_slice() {
+        [] | ["repl"] => repl::start(None),
+        ["repl", "--rc", rc] => repl::start(Some(rc)),
+        ["run", "-e", code] | ["-e", code] => run_inline(code),
+        ["run", path] => run(path),
+        ["check", paths @ ..] if !paths.is_empty() => check(paths),
+        ["tokens", path] => tokens(path),
+        ["ast", path] | ["ast", "--sexp", path] => ast(path, false),
+        ["ast", "--json", path] => ast(path, true),
+        ["fmt", "--check", paths @ ..] if !paths.is_empty() => fmt_check(paths),
+        ["fmt", path] => fmt(path),
+        ["test", "--bless", paths @ ..] if !paths.is_empty() => test(paths, true),
+        ["test", paths @ ..] if !paths.is_empty() => test(paths, false),
+        ["help"] | ["-h"] | ["--help"] => println!("{}", USAGE),
+        [path] if !is_command(path) => run(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+fn is_command(arg: &str) -> bool {
+    matches!(
+        arg,
+        "run" | "repl" | "check" | "tokens" | "ast" | "fmt" | "test" | "-e"
+    )
+}
+
+pub fn run(path: &str) {
+    let input = read_source(path);
+    execute(source_name(path), input);
+}
+
+pub fn run_inline(code: &str) {
+    execute("<eval>", code.to_string());
+}
+
+// Parses every file and reports all diagnostics, exiting with status 1 if
+// any file has errors. Nothing is evaluated.
+pub fn check(paths: &[&str]) {
+    let mut failed = false;
+    for path in paths {
+        let input = read_source(path);
+        failed |= parse(source_name(path), input).is_none();
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+pub fn tokens(path: &str) {
+    let input = read_source(path);
+    for tok in Lexer::new(input) {
+        println!("{}\t{:?}\t{:?}", tok.position, tok.token_type, tok.literal);
+    }
+}
+
+pub fn ast(path: &str, json: bool) {
+    if !json {
+        print!("{}", to_sexp(&parse_or_exit(path)));
+        return;
+    }
+    let input = read_source(path);
+    match source_to_json(&input) {
+        Ok(json) => println!("{}", json),
+        Err(errors) => {
+            report_errors(source_name(path), &errors);
+            process::exit(1);
+        }
+    }
+}
+
+pub fn fmt(path: &str) {
+    let input = read_source(path);
+    match format_source(&input) {
+        Ok(formatted) => print!("{}", formatted),
+        Err(errors) => {
+            report_errors(source_name(path), &errors);
+            process::exit(1);
+        }
+    }
+}
+
+// Reports every file whose formatting differs from the canonical output,
+// exiting with status 1 if there are any. Files are left untouched.
+pub fn fmt_check(paths: &[&str]) {
+    let mut failed = false;
+    for path in paths {
+        let input = read_source(path);
+        match format_source(&input) {
+            Ok(formatted) if formatted == input => {}
+            Ok(_) => {
+                eprintln!("{}: not formatted", source_name(path));
+                failed = true;
+            }
+            Err(errors) => {
+                report_errors(source_name(path), &errors);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+// Replays transcript tests, exiting with status 1 if any output differs.
+// With `bless`, files are rewritten with the actual output instead.
+pub fn test(paths: &[&str], bless: bool) {
+    let mut failed = false;
+    for path in paths {
+        let input = read_source(path);
+        let name = source_name(path);
+        if bless {
+            let blessed = transcript::bless(&input);
+            if blessed != input {
+                if let Err(e) = fs::write(path, blessed) {
+                    eprintln!("{}: unable to write: {}", name, e);
+                    process::exit(1);
+                }
+                println!("{}: blessed", name);
+            }
+        } else if let Some(report) = transcript::check(name, &input) {
+            eprint!("{}", report);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+// Parses and evaluates a whole program, printing its final value. Exits with
+// status 1 on parse or runtime errors.
+fn execute(name: &str, input: String) {
+    match Interpreter::new().eval_str(&input) {
+        Ok(Object::Null(_)) => {}
+        Ok(evaluated) => println!("{}", evaluated.inspect()),
+        Err(CrabError::Parse(errors)) => {
+            report_errors(name, &errors);
+            process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("{}: {}", name, error);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_or_exit(path: &str) -> Program {
+    let input = read_source(path);
+    match parse(source_name(path), input) {
+        Some(program) => program,
+        None => process::exit(1),
+    }
+}
+
+// Returns `None` after printing the parse errors, if there were any.
+fn parse(name: &str, input: String) -> Option<Program> {
+    let l = Lexer::new(input);
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+
+    if !p.errors().is_empty() {
+        report_errors(name, p.errors());
+        return None;
+    }
+    Some(program)
+}
+
+fn report_errors(name: &str, errors: &[String]) {
+    for e in errors {
+        eprintln!("{}:{}", name, e);
+    }
+}
+
+fn source_name(path: &str) -> &str {
+    match path {
+        "-" => "<stdin>",
+        _ => path,
+    }
+}
+
+// Reads a source file, or standard input when the path is `-`.
+fn read_source(path: &str) -> String {
+    let read = match path {
+        "-" => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map(|_| input)
+        }
+        _ => fs::read_to_string(path),
+    };
+    match read {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("error: unable to read {}: {}", source_name(path), e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Sign and magnitude, with the magnitude stored as little-endian base 2^32
+// limbs. Zero is an empty magnitude and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+impl BigInt {
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    /// Greatest common divisor, always non-negative.
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).unwrap();
+            a = b;
+            b = remainder;
+        }
+        a
+    }
+
+    // Bytes held by the magnitude.
+    pub fn heap_size(&self) -> usize {
+        self.magnitude.len() * std::mem::size_of::<u32>()
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let mut value: u64 = 0;
+        for (i, limb) in self.magnitude.iter().enumerate() {
+            value |= (*limb as u64) << (32 * i);
+        }
+        if self.negative {
+            0i64.checked_sub_unsigned(value)
+        } else {
+            i64::try_from(value).ok()
+        }
+    }
+
+    /// Truncating division, matching the semantics of `i64` division.
+    /// Returns `None` when dividing by zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    fn from_parts(negative: bool, magnitude: Vec<u32>) -> BigInt {
+        let magnitude = trim(magnitude);
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> BigInt {
+        let abs = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        self + -other
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off base 10^9 chunks, least significant first.
+        let mut chunks = Vec::new();
+        let mut rest = self.magnitude.clone();
+        while !rest.is_empty() {
+            let (quotient, remainder) = div_rem_magnitude(&rest, &[1_000_000_000]);
+            chunks.push(remainder.first().copied().unwrap_or(0));
+            rest = quotient;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// Requires a >= b.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, limb) in a.iter().enumerate() {
+        let mut diff = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let cur = result[i + j] as u64 + (*x as u64) * (*y as u64) + carry;
+            result[i + j] = cur as u32;
+            carry = cur >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = b {
+        let divisor = *divisor as u64;
+        let mut quotient = vec![0u32; a.len()];
+        let mut remainder = 0u64;
+        for i in (0..a.len()).rev() {
+            let cur = (remainder << 32) | a[i] as u64;
+            quotient[i] = (cur / divisor) as u32;
+            remainder = cur % divisor;
+        }
+        return (trim(quotient), trim(vec![remainder as u32]));
+    }
+
+    // Shift-and-subtract long division, one bit at a time.
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for i in (0..a.len() * 32).rev() {
+        remainder = shift_left_one(&remainder, (a[i / 32] >> (i % 32)) & 1);
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = trim(sub_magnitude(&remainder, b));
+            quotient[i / 32] |= 1 << (i % 32);
+        }
+    }
+    (trim(quotient), remainder)
+}
+
+fn shift_left_one(a: &[u32], low_bit: u32) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len() + 1);
+    let mut carry = low_bit;
+    for limb in a {
+        result.push((limb << 1) | carry);
+        carry = limb >> 31;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    trim(result)
+}
+
+fn trim(mut magnitude: Vec<u32>) -> Vec<u32> {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    magnitude
+}
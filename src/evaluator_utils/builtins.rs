@@ -0,0 +1,62 @@
+use super::bigint::BigInt;
+use super::evaluator::new_error;
+use super::object::{Builtin, BuiltinFunction, Null, Object, Rational};
+use super::runtime::Runtime;
+use std::sync::Arc;
+
+const BUILTINS: &[(&str, BuiltinFunction)] = &[("puts", puts), ("rational", rational)];
+
+pub fn get(name: &str, runtime: &Arc<Runtime>) -> Option<Object> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(name, function)| {
+            Object::Builtin(Builtin {
+                name: name.to_string(),
+                function: *function,
+                runtime: runtime.clone(),
+            })
+        })
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTINS.iter().map(|(name, _)| *name)
+}
+
+// Prints each argument on a line of its own.
+fn puts(args: Vec<Object>, runtime: &Runtime) -> Object {
+    for arg in &args {
+        if let Err(e) = runtime.print(&arg.inspect()) {
+            return new_error(format!("Cannot write output: {}", e));
+        }
+    }
+    Object::Null(Null {})
+}
+
+fn rational(args: Vec<Object>, _: &Runtime) -> Object {
+    if args.len() != 2 {
+        return new_error(format!(
+            "Wrong number of arguments to `rational`: got {}, want 2",
+            args.len()
+        ));
+    }
+    let mut parts = Vec::new();
+    for arg in &args {
+        match arg {
+            Object::Integer(i) => parts.push(BigInt::from(i.value)),
+            Object::BigInteger(i) => parts.push(i.value.clone()),
+            _ => {
+                return new_error(format!(
+                    "Argument to `rational` must be Integer, got {:?}",
+                    arg.object_type()
+                ))
+            }
+        }
+    }
+    let denominator = parts.pop().unwrap();
+    let numerator = parts.pop().unwrap();
+    if denominator.is_zero() {
+        return new_error("Division by zero".to_string());
+    }
+    Rational::new_object(numerator, denominator)
+}
@@ -1,26 +1,44 @@
 use super::object::Object;
+use super::runtime::Runtime;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq)]
+// Frames are shared rather than copied: cloning an environment, as every
+// function literal and call does, only bumps reference counts, and a frame
+// is copied when it is written to while shared. Outer frames are never
+// written through, so the prelude exists once per session.
+#[derive(Debug, Clone)]
 pub struct Environment {
-    store: HashMap<String, Object>,
-    outer: Option<Box<Environment>>,
+    store: Arc<HashMap<String, Object>>,
+    outer: Option<Arc<Environment>>,
+    runtime: Arc<Runtime>,
 }
 impl Environment {
     pub fn new() -> Environment {
+        Environment::with_runtime(Arc::new(Runtime::new()))
+    }
+
+    pub fn with_runtime(runtime: Arc<Runtime>) -> Environment {
         Environment {
-            store: HashMap::new(),
+            store: Arc::new(HashMap::new()),
             outer: None,
+            runtime,
         }
     }
 
     pub fn new_enclosed(outer: Environment) -> Environment {
+        let runtime = outer.runtime.clone();
         Environment {
-            store: HashMap::new(),
-            outer: Some(Box::new(outer)),
+            store: Arc::new(HashMap::new()),
+            outer: Some(Arc::new(outer)),
+            runtime,
         }
     }
 
+    pub fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
     pub fn get(&self, name: &str) -> Option<&Object> {
         let obj = self.store.get(name);
         obj.or_else(|| match &self.outer {
@@ -30,6 +48,49 @@ impl Environment {
     }
 
     pub fn set(&mut self, name: String, value: Object) {
-        self.store.insert(name, value);
+        Arc::make_mut(&mut self.store).insert(name, value);
+    }
+
+    // Approximate bytes taken by the bindings of this frame. Outer frames
+    // are shared with whoever made them, so they are left out.
+    pub fn heap_size(&self) -> usize {
+        self.store
+            .iter()
+            .map(|(name, value)| name.len() + value.heap_size())
+            .sum()
+    }
+
+    // The bindings of this frame, sorted by name. Outer frames are left out.
+    pub fn bindings(&self) -> Vec<(&str, &Object)> {
+        let mut bindings: Vec<(&str, &Object)> =
+            self.store.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        bindings.sort_by(|a, b| a.0.cmp(b.0));
+        bindings
+    }
+
+    // Every name visible from this frame, outer frames included, sorted and
+    // without duplicates.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.store.keys().map(|k| k.as_str()).collect();
+        if let Some(outer) = &self.outer {
+            names.extend(outer.names());
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+// Environments are equal when they hold the same bindings, whatever runtime
+// they belong to.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Environment) -> bool {
+        self.store == other.store && self.outer == other.outer
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment::new()
     }
 }
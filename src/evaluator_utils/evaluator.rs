@@ -1,10 +1,15 @@
 use super::{
+    bigint::BigInt,
+    builtins,
     environment::Environment,
-    object::{Boolean, Error, Function, Integer, Null, Object, ObjectType, Return},
+    object::{
+        BigInteger, Boolean, Error, Function, Integer, Native, Null, Object, ObjectType, Rational,
+        Return,
+    },
 };
 use crate::parser_utils::ast::{
-    BlockStatement, CallExpression, Expression, ExpressionStatement, IfExpression, LetStatement,
-    Node, Program, ReturnStatement, Statement,
+    BlockStatement, CallExpression, Expression, IfExpression, LetStatement, Node, ReturnStatement,
+    Statement,
 };
 
 pub fn eval(statements: &Vec<Node>, env: &mut Environment) -> Object {
@@ -51,6 +56,18 @@ fn evaluate_statement(node: &Statement, env: &mut Environment) -> Object {
 }
 
 fn evaluate_expression_statement(node: &Expression, env: &mut Environment) -> Object {
+    if let Err(interrupt) = env.runtime().step() {
+        return new_error(interrupt.to_string());
+    }
+    if let Err(interrupt) = env.runtime().nest() {
+        return new_error(interrupt.to_string());
+    }
+    let result = evaluate_expression(node, env);
+    env.runtime().unnest();
+    result
+}
+
+fn evaluate_expression(node: &Expression, env: &mut Environment) -> Object {
     match node {
         Expression::IntegerLiteral(i) => Object::Integer(Integer { value: i.value }),
         Expression::BooleanExpression(b) => Object::Boolean(Boolean { value: b.value }),
@@ -59,7 +76,7 @@ fn evaluate_expression_statement(node: &Expression, env: &mut Environment) -> Ob
             if is_error(&right) {
                 return right;
             }
-            eval_prefix_expression(&p.operator, right)
+            charge(eval_prefix_expression(&p.operator, right), env)
         }
         Expression::InfixExpression(ie) => {
             let left = evaluate_expression_statement(&ie.left, env);
@@ -70,21 +87,26 @@ fn evaluate_expression_statement(node: &Expression, env: &mut Environment) -> Ob
             if is_error(&right) {
                 return right;
             }
-            eval_infix_expression(&ie.operator, left, right)
+            charge(eval_infix_expression(&ie.operator, left, right), env)
         }
         Expression::BlockStatement(bs) => evaluate_block_statement(&bs, env),
         Expression::Identifier(id) => match env.get(&id.value) {
-            Some(obj) => obj.clone(),
-            None => new_error(format!("Identifier not found: {}", id.value)),
+            Some(obj) => charge(obj.clone(), env),
+            None => match builtins::get(&id.value, env.runtime()) {
+                Some(builtin) => builtin,
+                None => new_error(format!("Identifier not found: {}", id.value)),
+            },
         },
         Expression::IfExpression(ie) => eval_if_else_expression(&ie, env),
-        Expression::FunctionLiteral(fl) => Object::Function(Function {
-            parameters: fl.get_parameters(),
-            body: fl.body.clone(),
-            env: env.clone(),
-        }),
+        Expression::FunctionLiteral(fl) => charge(
+            Object::Function(Function {
+                parameters: fl.get_parameters(),
+                body: fl.body.clone(),
+                env: env.clone(),
+            }),
+            env,
+        ),
         Expression::CallExpression(ce) => eval_call_expression(&ce, env), // TODO: Implement recursive functions
-        _ => new_error(format!("Unknown expression: {:?}", node)),
     }
 }
 
@@ -109,7 +131,9 @@ fn eval_bang_prefix_operator_expression(right: Object) -> Object {
                 Object::Boolean(Boolean { value: true })
             }
         }
-        Object::Integer(_) => Object::Boolean(Boolean { value: false }),
+        Object::Integer(_) | Object::BigInteger(_) | Object::Rational(_) => {
+            Object::Boolean(Boolean { value: false })
+        }
         Object::Null(_) => Object::Boolean(Boolean { value: true }),
         _ => new_error(format!("Unknown operator: !{:?}", right.object_type())),
     }
@@ -117,18 +141,25 @@ fn eval_bang_prefix_operator_expression(right: Object) -> Object {
 
 fn eval_minus_prefix_operator_expression(right: Object) -> Object {
     match right {
-        Object::Integer(i) => Object::Integer(Integer { value: -i.value }),
+        Object::Integer(i) => match i.value.checked_neg() {
+            Some(value) => Object::Integer(Integer { value }),
+            None => BigInteger::new_object(-BigInt::from(i.value)),
+        },
+        Object::BigInteger(i) => BigInteger::new_object(-i.value),
+        Object::Rational(r) => Rational::new_object(-r.numerator, r.denominator),
         _ => new_error(format!("Unknown operator: -{:?}", right.object_type())),
     }
 }
 
 fn eval_infix_expression(operator: &String, left: Object, right: Object) -> Object {
     if left.object_type() == ObjectType::Integer && right.object_type() == ObjectType::Integer {
-        return eval_integer_infix_expression(
-            operator,
-            left.downcast().unwrap(),
-            right.downcast().unwrap(),
-        );
+        if let (Object::Integer(l), Object::Integer(r)) = (&left, &right) {
+            return eval_integer_infix_expression(operator, l.clone(), r.clone());
+        }
+        return eval_big_integer_infix_expression(operator, to_big_int(&left), to_big_int(&right));
+    }
+    if is_numeric(&left) && is_numeric(&right) {
+        return eval_rational_infix_expression(operator, to_fraction(&left), to_fraction(&right));
     }
     if left.object_type() != right.object_type() {
         return new_error(format!(
@@ -155,40 +186,142 @@ fn eval_infix_expression(operator: &String, left: Object, right: Object) -> Obje
 }
 
 fn eval_integer_infix_expression(operator: &String, left: Integer, right: Integer) -> Object {
+    let checked = match operator.as_str() {
+        "+" => left.value.checked_add(right.value),
+        "-" => left.value.checked_sub(right.value),
+        "*" => left.value.checked_mul(right.value),
+        "/" => left.value.checked_div(right.value),
+        "<" => {
+            return Object::Boolean(Boolean {
+                value: left.value < right.value,
+            })
+        }
+        ">" => {
+            return Object::Boolean(Boolean {
+                value: left.value > right.value,
+            })
+        }
+        "==" => {
+            return Object::Boolean(Boolean {
+                value: left.value == right.value,
+            })
+        }
+        "!=" => {
+            return Object::Boolean(Boolean {
+                value: left.value != right.value,
+            })
+        }
+        _ => {
+            return new_error(format!(
+                "Unknown operator: {:?} {} {:?}",
+                left.object_type(),
+                operator,
+                right.object_type(),
+            ))
+        }
+    };
+    match checked {
+        Some(value) => Object::Integer(Integer { value }),
+        // Overflowed (or divided by zero): redo it with arbitrary precision.
+        None => eval_big_integer_infix_expression(
+            operator,
+            BigInt::from(left.value),
+            BigInt::from(right.value),
+        ),
+    }
+}
+
+fn eval_big_integer_infix_expression(operator: &String, left: BigInt, right: BigInt) -> Object {
     match operator.as_str() {
-        "+" => Object::Integer(Integer {
-            value: left.value + right.value,
+        "+" => BigInteger::new_object(left + right),
+        "-" => BigInteger::new_object(left - right),
+        "*" => BigInteger::new_object(left * right),
+        "/" => match left.div_rem(&right) {
+            Some((quotient, _)) => BigInteger::new_object(quotient),
+            None => new_error("Division by zero".to_string()),
+        },
+        "<" => Object::Boolean(Boolean {
+            value: left < right,
         }),
-        "-" => Object::Integer(Integer {
-            value: left.value - right.value,
+        ">" => Object::Boolean(Boolean {
+            value: left > right,
         }),
-        "*" => Object::Integer(Integer {
-            value: left.value * right.value,
+        "==" => Object::Boolean(Boolean {
+            value: left == right,
         }),
-        "/" => Object::Integer(Integer {
-            value: left.value / right.value,
+        "!=" => Object::Boolean(Boolean {
+            value: left != right,
         }),
+        _ => new_error(format!(
+            "Unknown operator: {:?} {} {:?}",
+            ObjectType::Integer,
+            operator,
+            ObjectType::Integer,
+        )),
+    }
+}
+
+fn eval_rational_infix_expression(
+    operator: &String,
+    left: (BigInt, BigInt),
+    right: (BigInt, BigInt),
+) -> Object {
+    let (a, b) = left;
+    let (c, d) = right;
+    match operator.as_str() {
+        "+" => Rational::new_object(a * d.clone() + c * b.clone(), b * d),
+        "-" => Rational::new_object(a * d.clone() - c * b.clone(), b * d),
+        "*" => Rational::new_object(a * c, b * d),
+        "/" => {
+            if c.is_zero() {
+                return new_error("Division by zero".to_string());
+            }
+            Rational::new_object(a * d, b * c)
+        }
+        // Denominators are always positive, so cross-multiplying keeps order.
         "<" => Object::Boolean(Boolean {
-            value: left.value < right.value,
+            value: a * d < c * b,
         }),
         ">" => Object::Boolean(Boolean {
-            value: left.value > right.value,
+            value: a * d > c * b,
         }),
         "==" => Object::Boolean(Boolean {
-            value: left.value == right.value,
+            value: a * d == c * b,
         }),
         "!=" => Object::Boolean(Boolean {
-            value: left.value != right.value,
+            value: a * d != c * b,
         }),
         _ => new_error(format!(
             "Unknown operator: {:?} {} {:?}",
-            left.object_type(),
+            ObjectType::Rational,
             operator,
-            right.object_type(),
+            ObjectType::Rational,
         )),
     }
 }
 
+fn is_numeric(obj: &Object) -> bool {
+    matches!(
+        obj.object_type(),
+        ObjectType::Integer | ObjectType::Rational
+    )
+}
+
+fn to_fraction(obj: &Object) -> (BigInt, BigInt) {
+    match obj {
+        Object::Rational(r) => (r.numerator.clone(), r.denominator.clone()),
+        _ => (to_big_int(obj), BigInt::from(1)),
+    }
+}
+
+fn to_big_int(obj: &Object) -> BigInt {
+    match obj {
+        Object::Integer(i) => BigInt::from(i.value),
+        Object::BigInteger(i) => i.value.clone(),
+        _ => panic!("Expected an integer, got {:?}", obj.object_type()),
+    }
+}
+
 fn eval_if_else_expression(ie: &IfExpression, env: &mut Environment) -> Object {
     let condition = evaluate_expression_statement(&ie.condition, env);
     if is_error(&condition) {
@@ -248,16 +381,54 @@ fn eval_call_expression(ce: &CallExpression, env: &mut Environment) -> Object {
     apply_function(function, args)
 }
 
-fn apply_function(function: Object, args: Vec<Object>) -> Object {
+// Calls any callable value: a Crab function, a builtin or a native function
+// registered by the host.
+pub fn apply_function(function: Object, args: Vec<Object>) -> Object {
+    if let Object::Builtin(builtin) = function {
+        return (builtin.function)(args, &builtin.runtime);
+    }
+    if let Object::Native(native) = function {
+        return apply_native(&native, args);
+    }
     if function.object_type() != ObjectType::Function {
         return new_error(format!("Not a function: {:?}", function.object_type()));
     }
-    let func_obj = function.downcast().unwrap();
+    let func_obj: Function = function.downcast().unwrap();
+    if args.len() != func_obj.parameters.len() {
+        return new_error(format!(
+            "Wrong number of arguments: got {}, want {}",
+            args.len(),
+            func_obj.parameters.len()
+        ));
+    }
+    let runtime = func_obj.env.runtime().clone();
     let mut extended_env = extend_function_env(&func_obj, args);
+    if let Err(interrupt) = runtime.allocate(|| extended_env.heap_size()) {
+        return new_error(interrupt.to_string());
+    }
+    if let Err(interrupt) = runtime.enter() {
+        return new_error(interrupt.to_string());
+    }
     let evaluated = eval(&func_obj.body.statements, &mut extended_env);
+    runtime.exit();
     unwrap_return_value(evaluated)
 }
 
+fn apply_native(native: &Native, args: Vec<Object>) -> Object {
+    if !native.arity.accepts(args.len()) {
+        return new_error(format!(
+            "Wrong number of arguments to `{}`: got {}, want {}",
+            native.name,
+            args.len(),
+            native.arity
+        ));
+    }
+    match (native.function)(args) {
+        Ok(value) => value,
+        Err(message) => new_error(format!("{}: {}", native.name, message)),
+    }
+}
+
 fn extend_function_env(function: &Function, args: Vec<Object>) -> Environment {
     let mut env = Environment::new_enclosed(function.env.clone());
     for (i, param) in function.parameters.iter().enumerate() {
@@ -273,7 +444,15 @@ fn unwrap_return_value(obj: Object) -> Object {
     obj
 }
 
-fn new_error(msg: String) -> Object {
+// Counts a newly made value towards the runtime's memory limit.
+fn charge(obj: Object, env: &Environment) -> Object {
+    match env.runtime().allocate(|| obj.heap_size()) {
+        Ok(()) => obj,
+        Err(interrupt) => new_error(interrupt.to_string()),
+    }
+}
+
+pub fn new_error(msg: String) -> Object {
     Object::Error(Error { message: msg })
 }
 
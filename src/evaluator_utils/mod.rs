@@ -1,5 +1,17 @@
+mod bigint;
+pub mod builtins;
+#[allow(
+    clippy::needless_borrow,
+    clippy::needless_return,
+    clippy::redundant_closure
+)]
 pub mod evaluator;
 pub mod environment;
-mod object;
+#[allow(clippy::manual_map)]
+pub mod object;
+pub mod prelude;
+pub mod runtime;
+#[cfg(feature = "serde")]
+mod serialize;
 #[cfg(test)]
 pub mod tests;
\ No newline at end of file
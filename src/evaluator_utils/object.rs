@@ -1,15 +1,22 @@
 use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
 
 use crate::parser_utils::ast::BlockStatement;
 
+use super::bigint::BigInt;
 use super::environment::Environment;
+use super::runtime::Runtime;
 
 #[derive(Debug, PartialEq)]
 pub enum ObjectType {
     Integer,
+    Rational,
     Boolean,
     Return,
     Function,
+    Builtin,
+    Native,
     Null,
     Error,
 }
@@ -17,9 +24,13 @@ pub enum ObjectType {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     Integer(Integer),
+    BigInteger(BigInteger),
+    Rational(Rational),
     Boolean(Boolean),
     Return(Return),
     Function(Function),
+    Builtin(Builtin),
+    Native(Native),
     Null(Null),
     Error(Error),
 }
@@ -27,9 +38,13 @@ impl Object {
     pub fn object_type(&self) -> ObjectType {
         match self {
             Object::Integer(i) => i.object_type(),
+            Object::BigInteger(i) => i.object_type(),
+            Object::Rational(r) => r.object_type(),
             Object::Boolean(b) => b.object_type(),
             Object::Return(r) => r.object_type(),
             Object::Function(f) => f.object_type(),
+            Object::Builtin(b) => b.object_type(),
+            Object::Native(n) => n.object_type(),
             Object::Null(n) => n.object_type(),
             Object::Error(e) => e.object_type(),
         }
@@ -37,9 +52,13 @@ impl Object {
     pub fn inspect(&self) -> String {
         match self {
             Object::Integer(i) => i.inspect(),
+            Object::BigInteger(i) => i.inspect(),
+            Object::Rational(r) => r.inspect(),
             Object::Boolean(b) => b.inspect(),
             Object::Return(r) => r.inspect(),
             Object::Function(f) => f.inspect(),
+            Object::Builtin(b) => b.inspect(),
+            Object::Native(n) => n.inspect(),
             Object::Null(n) => n.inspect(),
             Object::Error(e) => e.inspect(),
         }
@@ -48,9 +67,13 @@ impl Object {
     pub fn downcast<T: Any>(self) -> Option<T> {
         let obj: Box<dyn Any> = match self {
             Object::Integer(i) => Box::new(i),
+            Object::BigInteger(i) => Box::new(i),
+            Object::Rational(r) => Box::new(r),
             Object::Boolean(b) => Box::new(b),
             Object::Return(r) => Box::new(r),
             Object::Function(f) => Box::new(f),
+            Object::Builtin(b) => Box::new(b),
+            Object::Native(n) => Box::new(n),
             Object::Null(n) => Box::new(n),
             Object::Error(e) => Box::new(e),
         };
@@ -61,6 +84,24 @@ impl Object {
         }
     }
 
+    // Approximate bytes a copy of the value takes, counting what it owns:
+    // digits of big numbers, and a function's parameter names. The
+    // environment a function closes over is shared, so copies don't pay
+    // for it again.
+    pub fn heap_size(&self) -> usize {
+        let owned = match self {
+            Object::BigInteger(i) => i.value.heap_size(),
+            Object::Rational(r) => r.numerator.heap_size() + r.denominator.heap_size(),
+            Object::Return(r) => r.value.heap_size(),
+            Object::Function(f) => f.parameters.iter().map(String::len).sum::<usize>(),
+            Object::Builtin(b) => b.name.len(),
+            Object::Native(n) => n.name.len(),
+            Object::Error(e) => e.message.len(),
+            Object::Integer(_) | Object::Boolean(_) | Object::Null(_) => 0,
+        };
+        std::mem::size_of::<Object>() + owned
+    }
+
     pub fn get_return_value(&self) -> Object {
         match self {
             Object::Return(r) => *r.value.clone(),
@@ -82,6 +123,58 @@ impl Integer {
     }
 }
 
+// Integers outside the i64 range. Arithmetic demotes results back to
+// `Integer` whenever they fit, so a `BigInteger` never holds a small value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BigInteger {
+    pub value: BigInt,
+}
+impl BigInteger {
+    pub fn new_object(value: BigInt) -> Object {
+        match value.to_i64() {
+            Some(value) => Object::Integer(Integer { value }),
+            None => Object::BigInteger(BigInteger { value }),
+        }
+    }
+    fn inspect(&self) -> String {
+        self.value.to_string()
+    }
+    pub fn object_type(&self) -> ObjectType {
+        ObjectType::Integer
+    }
+}
+
+// Exact fraction kept in lowest terms with a positive denominator. Whole
+// values are demoted to `Integer`/`BigInteger` on construction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rational {
+    pub numerator: BigInt,
+    pub denominator: BigInt,
+}
+impl Rational {
+    pub fn new_object(numerator: BigInt, denominator: BigInt) -> Object {
+        let mut gcd = numerator.gcd(&denominator);
+        if denominator.is_negative() {
+            gcd = -gcd;
+        }
+        let (numerator, _) = numerator.div_rem(&gcd).unwrap();
+        let (denominator, _) = denominator.div_rem(&gcd).unwrap();
+        if denominator == BigInt::from(1) {
+            return BigInteger::new_object(numerator);
+        }
+        Object::Rational(Rational {
+            numerator,
+            denominator,
+        })
+    }
+    fn inspect(&self) -> String {
+        format!("{}/{}", self.numerator, self.denominator)
+    }
+    pub fn object_type(&self) -> ObjectType {
+        ObjectType::Rational
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Boolean {
     pub value: bool,
@@ -127,6 +220,90 @@ impl Function {
     }
 }
 
+pub type BuiltinFunction = fn(Vec<Object>, &Runtime) -> Object;
+
+// A function built into the language. It is bound to the runtime of the
+// environment it was looked up in, which it can print through.
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub function: BuiltinFunction,
+    pub runtime: Arc<Runtime>,
+}
+impl Builtin {
+    fn inspect(&self) -> String {
+        format!("builtin {}", self.name)
+    }
+    pub fn object_type(&self) -> ObjectType {
+        ObjectType::Builtin
+    }
+}
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Builtin) -> bool {
+        self.name == other.name
+    }
+}
+
+pub type NativeFunction = dyn Fn(Vec<Object>) -> Result<Object, String> + Send + Sync;
+
+// How many arguments a native function takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::AtLeast(n) => count >= n,
+        }
+    }
+}
+impl From<usize> for Arity {
+    fn from(n: usize) -> Arity {
+        Arity::Exact(n)
+    }
+}
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+        }
+    }
+}
+
+// A function provided by the host program. Unlike `Builtin` it can capture
+// state, so it is shared rather than copied.
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub arity: Arity,
+    pub function: Arc<NativeFunction>,
+}
+impl Native {
+    fn inspect(&self) -> String {
+        format!("native {}", self.name)
+    }
+    pub fn object_type(&self) -> ObjectType {
+        ObjectType::Native
+    }
+}
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+impl PartialEq for Native {
+    fn eq(&self, other: &Native) -> bool {
+        Arc::ptr_eq(&self.function, &other.function)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Null {}
 impl Null {
@@ -0,0 +1,31 @@
+use super::environment::Environment;
+use super::evaluator::eval;
+use super::object::Object;
+use super::runtime::Runtime;
+use crate::lexer_utils::lexer::Lexer;
+use crate::parser_utils::parser::Parser;
+use std::sync::Arc;
+
+pub const PRELUDE: &str = include_str!("prelude.crb");
+
+// A fresh session environment: an empty frame enclosing one that holds the
+// prelude, so user bindings shadow prelude ones and can be listed apart.
+pub fn environment() -> Environment {
+    environment_with(Arc::new(Runtime::new()))
+}
+
+// A fresh session environment that belongs to an existing runtime.
+pub fn environment_with(runtime: Arc<Runtime>) -> Environment {
+    let mut prelude = Environment::with_runtime(runtime);
+    let mut p = Parser::new(Lexer::new(PRELUDE.to_string()));
+    let program = p.parse_program();
+    assert!(
+        p.errors().is_empty(),
+        "prelude doesn't parse: {:?}",
+        p.errors()
+    );
+    if let Object::Error(e) = eval(&program.statements, &mut prelude) {
+        panic!("prelude doesn't evaluate: {}", e.message);
+    }
+    Environment::new_enclosed(prelude)
+}
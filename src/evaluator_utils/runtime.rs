@@ -0,0 +1,285 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// How deep calls may nest unless the host says otherwise. Each Crab call
+// takes several Rust frames, and in a debug build this keeps them within the
+// 2 MiB stack of a spawned thread. Hosts on bigger stacks can allow more.
+pub const DEFAULT_MAX_DEPTH: usize = 100;
+
+// How deep expressions being evaluated may nest, counting through calls, so
+// the stack stays bounded whatever the limits are. A call nests its body one
+// level below the call, so this leaves room for DEFAULT_MAX_DEPTH calls.
+pub const MAX_EVAL_NESTING: usize = 200;
+
+// Where printing builtins write. Anything that implements `io::Write` is an
+// output, so a sink can be stdout, a `Vec<u8>`, a file or `io::sink()`.
+pub trait Output: Send {
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+impl<W: Write + Send> Output for W {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self, "{}", line)?;
+        self.flush()
+    }
+}
+
+// An output whose clones share one buffer, so the host can keep a handle to
+// read back what a script printed after handing the sink to the runtime.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+impl OutputBuffer {
+    pub fn new() -> OutputBuffer {
+        OutputBuffer::default()
+    }
+
+    // Returns everything written so far and empties the buffer.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes.lock().unwrap());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Bounds on a single evaluation, for running scripts that can't be trusted
+// to finish. `None` means unbounded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    // Expressions evaluated.
+    pub max_steps: Option<u64>,
+    // Function calls in progress at once.
+    pub max_depth: Option<usize>,
+    // Approximate bytes allocated for values and environments.
+    pub max_heap: Option<usize>,
+}
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_steps: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_heap: None,
+        }
+    }
+}
+
+// A flag another thread can raise to stop an evaluation. Clones share the
+// flag, and once raised it stays raised.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// Why the runtime stopped an evaluation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    StepLimit,
+    DepthLimit,
+    NestingLimit,
+    HeapLimit,
+    Cancelled,
+    Timeout,
+}
+impl fmt::Display for Interrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Interrupt::StepLimit => write!(f, "Step limit exceeded"),
+            Interrupt::DepthLimit => write!(f, "Call depth limit exceeded"),
+            Interrupt::NestingLimit => write!(f, "Nesting limit exceeded"),
+            Interrupt::HeapLimit => write!(f, "Memory limit exceeded"),
+            Interrupt::Cancelled => write!(f, "Evaluation cancelled"),
+            Interrupt::Timeout => write!(f, "Evaluation timed out"),
+        }
+    }
+}
+
+// What the runtime checks at every step, kept together so a step takes one
+// lock.
+#[derive(Debug, Default)]
+struct Bounds {
+    limits: Limits,
+    deadline: Option<Instant>,
+    cancel: CancelToken,
+}
+
+// State shared by everything evaluated in one session. Every frame of an
+// environment, and every function closed over one, points to the same
+// runtime as the root frame it was built from.
+pub struct Runtime {
+    output: Mutex<Box<dyn Output>>,
+    bounds: Mutex<Bounds>,
+    steps: AtomicU64,
+    depth: AtomicUsize,
+    nesting: AtomicUsize,
+    heap: AtomicUsize,
+    interrupt: Mutex<Option<Interrupt>>,
+}
+impl Runtime {
+    pub fn new() -> Runtime {
+        Runtime {
+            output: Mutex::new(Box::new(io::stdout())),
+            bounds: Mutex::new(Bounds::default()),
+            steps: AtomicU64::new(0),
+            depth: AtomicUsize::new(0),
+            nesting: AtomicUsize::new(0),
+            heap: AtomicUsize::new(0),
+            interrupt: Mutex::new(None),
+        }
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.bounds.lock().unwrap().limits
+    }
+
+    pub fn set_limits(&self, limits: Limits) {
+        self.bounds.lock().unwrap().limits = limits;
+    }
+
+    // Evaluations still running at `deadline` stop with a timeout.
+    pub fn set_deadline(&self, deadline: Option<Instant>) {
+        self.bounds.lock().unwrap().deadline = deadline;
+    }
+
+    pub fn cancel_token(&self) -> CancelToken {
+        self.bounds.lock().unwrap().cancel.clone()
+    }
+
+    // Replaces the token that stops evaluations, e.g. with a fresh one
+    // after the old one was used.
+    pub fn set_cancel_token(&self, token: CancelToken) {
+        self.bounds.lock().unwrap().cancel = token;
+    }
+
+    // Starts the budget of a new top-level evaluation.
+    pub fn reset(&self) {
+        self.steps.store(0, Ordering::Relaxed);
+        self.depth.store(0, Ordering::Relaxed);
+        self.nesting.store(0, Ordering::Relaxed);
+        self.heap.store(0, Ordering::Relaxed);
+        *self.interrupt.lock().unwrap() = None;
+    }
+
+    // What stopped the current evaluation, if the runtime did.
+    pub fn interrupt(&self) -> Option<Interrupt> {
+        *self.interrupt.lock().unwrap()
+    }
+
+    // Counts one evaluation step, and stops if the evaluation was cancelled
+    // or ran out of time or steps.
+    pub fn step(&self) -> Result<(), Interrupt> {
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        let bounds = self.bounds.lock().unwrap();
+        if bounds.cancel.is_cancelled() {
+            return self.stop(Interrupt::Cancelled);
+        }
+        if bounds
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return self.stop(Interrupt::Timeout);
+        }
+        match bounds.limits.max_steps {
+            Some(max) if steps > max => self.stop(Interrupt::StepLimit),
+            _ => Ok(()),
+        }
+    }
+
+    // Enters a function call. Every successful `enter` must be paired with
+    // an `exit`.
+    pub fn enter(&self) -> Result<(), Interrupt> {
+        let depth = self.depth.load(Ordering::Relaxed);
+        match self.limits().max_depth {
+            Some(max) if depth >= max => self.stop(Interrupt::DepthLimit),
+            _ => {
+                self.depth.store(depth + 1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn exit(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Enters the evaluation of an expression. Every successful `nest` must
+    // be paired with an `unnest`.
+    pub fn nest(&self) -> Result<(), Interrupt> {
+        let nesting = self.nesting.load(Ordering::Relaxed);
+        if nesting >= MAX_EVAL_NESTING {
+            return self.stop(Interrupt::NestingLimit);
+        }
+        self.nesting.store(nesting + 1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn unnest(&self) {
+        self.nesting.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Counts the bytes `size` returns towards the memory limit. Sizing a
+    // value takes time, so `size` only runs when there is a limit.
+    pub fn allocate(&self, size: impl FnOnce() -> usize) -> Result<(), Interrupt> {
+        let max = match self.limits().max_heap {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+        let bytes = size();
+        let heap = self
+            .heap
+            .fetch_add(bytes, Ordering::Relaxed)
+            .saturating_add(bytes);
+        if heap > max {
+            return self.stop(Interrupt::HeapLimit);
+        }
+        Ok(())
+    }
+
+    fn stop(&self, interrupt: Interrupt) -> Result<(), Interrupt> {
+        *self.interrupt.lock().unwrap() = Some(interrupt);
+        Err(interrupt)
+    }
+
+    pub fn set_output(&self, output: Box<dyn Output>) {
+        *self.output.lock().unwrap() = output;
+    }
+
+    pub fn print(&self, line: &str) -> io::Result<()> {
+        self.output.lock().unwrap().write_line(line)
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Runtime {
+        Runtime::new()
+    }
+}
+
+impl fmt::Debug for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Runtime").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,141 @@
+// Serde support for the values Crab code works with, enabled by the `serde`
+// feature. Integers, booleans and null map to their serde counterparts, and
+// a rational to a `{"numerator": n, "denominator": d}` map; anything else,
+// such as a function, fails with an error naming its type rather than being
+// dropped.
+
+use super::bigint::BigInt;
+use super::object::{BigInteger, Boolean, Integer, Null, Object, Rational};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Object::Integer(i) => serializer.serialize_i64(i.value),
+            // Beyond i64 serde still has 128-bit integers.
+            Object::BigInteger(i) => match i.value.to_string().parse::<i128>() {
+                Ok(value) => serializer.serialize_i128(value),
+                Err(_) => Err(ser::Error::custom(format!(
+                    "Integer {} is too large to serialize",
+                    i.value
+                ))),
+            },
+            Object::Rational(r) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("numerator", &BigInteger::new_object(r.numerator.clone()))?;
+                map.serialize_entry(
+                    "denominator",
+                    &BigInteger::new_object(r.denominator.clone()),
+                )?;
+                map.end()
+            }
+            Object::Boolean(b) => serializer.serialize_bool(b.value),
+            Object::Null(_) => serializer.serialize_unit(),
+            value => Err(ser::Error::custom(format!(
+                "Cannot serialize a value of type {:?}",
+                value.object_type()
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Object, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an integer, a rational, a boolean or null")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Object, E> {
+        Ok(Object::Integer(Integer { value }))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Object, E> {
+        self.visit_u128(value.into())
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<Object, E> {
+        // Built from 32-bit chunks of the magnitude, most significant first.
+        let magnitude = value.unsigned_abs();
+        let mut big = BigInt::from(0);
+        for shift in [96, 64, 32, 0] {
+            let chunk = ((magnitude >> shift) & 0xffff_ffff) as i64;
+            big = big * BigInt::from(1 << 32) + BigInt::from(chunk);
+        }
+        if value < 0 {
+            big = -big;
+        }
+        Ok(BigInteger::new_object(big))
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<Object, E> {
+        match i128::try_from(value) {
+            Ok(value) => self.visit_i128(value),
+            Err(_) => Err(E::custom(format!(
+                "Integer {} is too large to deserialize",
+                value
+            ))),
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Object, E> {
+        Ok(Object::Boolean(Boolean { value }))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null(Null {}))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null(Null {}))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Object, D::Error> {
+        Object::deserialize(deserializer)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+        let mut numerator = None;
+        let mut denominator = None;
+        while let Some(key) = map.next_key::<String>()? {
+            let (part, field) = match key.as_str() {
+                "numerator" => (&mut numerator, FIELDS[0]),
+                "denominator" => (&mut denominator, FIELDS[1]),
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            };
+            if part.is_some() {
+                return Err(de::Error::duplicate_field(field));
+            }
+            *part = Some(integer(map.next_value()?)?);
+        }
+        let numerator = numerator.ok_or_else(|| de::Error::missing_field("numerator"))?;
+        let denominator = denominator.ok_or_else(|| de::Error::missing_field("denominator"))?;
+        if denominator.is_zero() {
+            return Err(de::Error::custom("Division by zero"));
+        }
+        Ok(Rational::new_object(numerator, denominator))
+    }
+}
+
+const FIELDS: &[&str] = &["numerator", "denominator"];
+
+fn integer<E: de::Error>(value: Object) -> Result<BigInt, E> {
+    match value {
+        Object::Integer(i) => Ok(BigInt::from(i.value)),
+        Object::BigInteger(i) => Ok(i.value),
+        value => Err(E::custom(format!(
+            "Rational parts must be Integer, got {:?}",
+            value.object_type()
+        ))),
+    }
+}
@@ -7,7 +7,10 @@ use crate::parser_utils::parser::Parser;
 
 use super::environment::Environment;
 use super::evaluator::eval;
-use super::object::{Boolean, Error, Integer, Null, Object};
+use super::object::{Boolean, Integer, Null, Object};
+use super::prelude;
+use super::runtime::{Interrupt, Limits, Output, OutputBuffer};
+use std::io;
 
 fn evaluate(input: String) -> Object {
     let l = Lexer::new(input);
@@ -366,6 +369,7 @@ fn test_eval_function_application() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_eval_function_object() {
     let input = "fn(x) { x + 2; };".to_string();
     let res = evaluate(input);
@@ -401,6 +405,327 @@ fn test_closures() {
     test_integer_object(res, 5);
 }
 
+#[test]
+fn test_big_integer_arithmetic() {
+    struct EvalBigInt {
+        input: String,
+        expected: String,
+    }
+    let tests = vec![
+        EvalBigInt {
+            input: "9223372036854775807 + 1".to_string(),
+            expected: "9223372036854775808".to_string(),
+        },
+        EvalBigInt {
+            input: "let a = 1000000000000; a * a * a".to_string(),
+            expected: "1000000000000000000000000000000000000".to_string(),
+        },
+        EvalBigInt {
+            input: "-(-9223372036854775807 - 1)".to_string(),
+            expected: "9223372036854775808".to_string(),
+        },
+        EvalBigInt {
+            input: "let a = 1000000000000000; a * a * a * a / 1000000000007".to_string(),
+            expected: "999999999993000000000048999999999657000000002400".to_string(),
+        },
+        EvalBigInt {
+            input: "let a = 1000000000000000; -(a * a * a * a) / 7".to_string(),
+            expected: "-142857142857142857142857142857142857142857142857142857142857".to_string(),
+        },
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.input.clone());
+        assert_eq!(
+            res.object_type(),
+            ObjectType::Integer,
+            "Test [{}] - Object is not an Integer",
+            tt.input
+        );
+        assert_eq!(
+            res.inspect(),
+            tt.expected,
+            "Test [{}] - Wrong value",
+            tt.input
+        );
+    }
+}
+
+#[test]
+fn test_big_integer_demotion() {
+    let tests = vec![
+        ("-9223372036854775807 - 1", i64::MIN),
+        ("(9223372036854775807 + 10) - 20", 9223372036854775797),
+        ("let a = 1000000000000; a * a * a / (a * a)", 1000000000000),
+        ("let a = 4611686018427387904; a * 4 - a * 4", 0),
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.0.to_string());
+        test_integer_object(res, tt.1)
+    }
+}
+
+#[test]
+fn test_big_integer_comparison() {
+    let tests = vec![
+        ("9223372036854775807 + 1 > 9223372036854775807", true),
+        ("9223372036854775807 + 1 < 9223372036854775807", false),
+        ("let a = 4611686018427387904; a * 4 == a * 2 * 2", true),
+        ("let a = 4611686018427387904; a * 4 != a * 2 * 2", false),
+        ("let a = 4611686018427387904; a * 4 < -a * 4", false),
+        ("!(9223372036854775807 + 1)", false),
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.0.to_string());
+        test_boolean_object(res, tt.1)
+    }
+}
+
+#[test]
+fn test_rational_arithmetic() {
+    let tests = vec![
+        ("rational(1, 3)", "1/3"),
+        ("rational(2, -4)", "-1/2"),
+        ("-rational(1, 3)", "-1/3"),
+        ("rational(1, 3) + rational(1, 6)", "1/2"),
+        ("rational(1, 3) - rational(1, 2)", "-1/6"),
+        ("rational(2, 3) * rational(3, 4)", "1/2"),
+        ("rational(1, 3) / rational(2, 3)", "1/2"),
+        ("1 + rational(1, 2)", "3/2"),
+        ("rational(1, 2) / 5", "1/10"),
+        (
+            "rational(1, 9223372036854775807) * rational(1, 2)",
+            "1/18446744073709551614",
+        ),
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.0.to_string());
+        assert_eq!(
+            res.object_type(),
+            ObjectType::Rational,
+            "Test [{}] - Object is not a Rational",
+            tt.0
+        );
+        assert_eq!(res.inspect(), tt.1, "Test [{}] - Wrong value", tt.0);
+    }
+}
+
+#[test]
+fn test_rational_demotion() {
+    let tests = vec![
+        ("rational(6, 3)", 2),
+        ("rational(1, 3) * 3", 1),
+        ("rational(1, 2) + rational(1, 2)", 1),
+        ("rational(-4, 2)", -2),
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.0.to_string());
+        test_integer_object(res, tt.1)
+    }
+}
+
+#[test]
+fn test_rational_comparison() {
+    let tests = vec![
+        ("rational(1, 3) < rational(1, 2)", true),
+        ("rational(1, 3) > rational(1, 2)", false),
+        ("rational(2, 4) == rational(1, 2)", true),
+        ("rational(1, 2) != rational(1, 2)", false),
+        ("rational(-1, 2) < 0", true),
+        ("1 > rational(2, 3)", true),
+        ("!rational(1, 3)", false),
+    ];
+
+    for tt in tests {
+        let res = evaluate(tt.0.to_string());
+        test_boolean_object(res, tt.1)
+    }
+}
+
+#[test]
+fn test_prelude() {
+    let tests = vec![
+        ("identity(5)", 5),
+        ("compose(fn(x) { x * 2 }, fn(x) { x + 1 })(3)", 8),
+        ("flip(fn(a, b) { a - b })(1, 10)", 9),
+        ("abs(-7) + abs(7)", 14),
+        ("min(3, 9) * max(3, 9)", 27),
+        ("let min = fn(a, b) { 0 }; min(3, 9)", 0),
+    ];
+
+    for tt in tests {
+        let l = Lexer::new(tt.0.to_string());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        let res = eval(&program.statements, &mut prelude::environment());
+        test_integer_object(res, tt.1)
+    }
+
+    let env = prelude::environment();
+    assert!(
+        env.bindings().is_empty(),
+        "Prelude should sit in an outer frame"
+    );
+    assert!(env.get("compose").is_some(), "Prelude should be visible");
+}
+
+#[test]
+fn test_prelude_is_shared() {
+    // Every function literal and call clones its environment, so copying
+    // the prelude along with it made this program about 100 times slower
+    // than without it. Sharing the frame keeps the two close.
+    let input = "let t = fn(g) { fn() { g(); g() } }; let n = fn() { 1 };
+        t(t(t(t(t(t(t(t(t(t(n))))))))))()";
+    let l = Lexer::new(input.to_string());
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+
+    let fastest = |make_env: fn() -> Environment| {
+        (0..5)
+            .map(|_| {
+                let mut env = make_env();
+                let start = std::time::Instant::now();
+                test_integer_object(eval(&program.statements, &mut env), 1);
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    };
+    let bare = fastest(Environment::new);
+    let with_prelude = fastest(prelude::environment);
+    assert!(
+        with_prelude < bare * 4,
+        "Prelude made calls slower: {:?} against {:?}",
+        with_prelude,
+        bare
+    );
+}
+
+#[test]
+fn test_puts() {
+    let tests = vec![
+        ("puts(1)", "1\n"),
+        ("puts(1, true, rational(1, 2))", "1\ntrue\n1/2\n"),
+        ("puts()", ""),
+        (
+            "let f = fn(x) { puts(x); x * 2 }; puts(f(f(1)))",
+            "1\n2\n4\n",
+        ),
+        ("compose(puts, abs)(-3)", "3\n"),
+        ("puts(puts)", "builtin puts\n"),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        let printed = OutputBuffer::new();
+        let mut env = prelude::environment();
+        env.runtime().set_output(Box::new(printed.clone()));
+        let l = Lexer::new(input.to_string());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        let res = eval(&program.statements, &mut env);
+        test_null_object(res);
+        assert_eq!(printed.take(), expected, "Test [{}] - wrong output", i);
+    }
+}
+
+#[test]
+fn test_output_sink() {
+    struct Failing;
+    impl Output for Failing {
+        fn write_line(&mut self, _: &str) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+    }
+
+    let mut env = Environment::new();
+    let l = Lexer::new("let say = fn(x) { puts(x) };".to_string());
+    let program = Parser::new(l).parse_program();
+    eval(&program.statements, &mut env);
+
+    // Functions defined before the sink changes print to the new one.
+    let printed = OutputBuffer::new();
+    env.runtime().set_output(Box::new(printed.clone()));
+    let program = Parser::new(Lexer::new("say(5)".to_string())).parse_program();
+    eval(&program.statements, &mut env);
+    assert_eq!(printed.take(), "5\n");
+
+    env.runtime().set_output(Box::new(Failing));
+    let res = eval(&program.statements, &mut env);
+    match res {
+        Object::Error(e) => assert_eq!(e.message, "Cannot write output: closed"),
+        res => panic!("Expected an error, got {}", res.inspect()),
+    }
+}
+
+// Which limit stops which program is covered by the interpreter tests; this
+// checks the Error object each interrupt becomes and what the runtime records.
+#[test]
+fn test_limits() {
+    // Each call nests 41 levels deeper than the last.
+    let deep = format!(
+        "let d = fn(f) {{ {}f(f){} }}; d(d)",
+        "-(".repeat(40),
+        ")".repeat(40)
+    );
+    let tests = vec![
+        (
+            Limits {
+                max_steps: Some(5),
+                ..Limits::default()
+            },
+            "1 + 2 + 3 + 4",
+            "Step limit exceeded",
+            Some(Interrupt::StepLimit),
+        ),
+        (
+            Limits {
+                max_depth: Some(3),
+                ..Limits::default()
+            },
+            "let w = fn(f) { f(f) }; w(w)",
+            "Call depth limit exceeded",
+            Some(Interrupt::DepthLimit),
+        ),
+        (
+            Limits {
+                max_depth: None,
+                ..Limits::default()
+            },
+            &deep,
+            "Nesting limit exceeded",
+            Some(Interrupt::NestingLimit),
+        ),
+        (
+            Limits {
+                max_heap: Some(1000),
+                ..Limits::default()
+            },
+            "let sq = fn(x) { x * x }; sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(2))))))))))))",
+            "Memory limit exceeded",
+            Some(Interrupt::HeapLimit),
+        ),
+        (Limits::default(), "1 / 0", "Division by zero", None),
+    ];
+    for (i, (limits, input, expected, interrupt)) in tests.into_iter().enumerate() {
+        let mut env = Environment::new();
+        env.runtime().set_limits(limits);
+        let program = Parser::new(Lexer::new(input.to_string())).parse_program();
+        match eval(&program.statements, &mut env) {
+            Object::Error(e) => assert_eq!(e.message, expected, "Test [{}] - wrong error", i),
+            res => panic!("Test [{}] - expected an error, got {}", i, res.inspect()),
+        }
+        assert_eq!(
+            env.runtime().interrupt(),
+            interrupt,
+            "Test [{}] - runtime recorded the wrong interrupt",
+            i
+        );
+    }
+}
+
 #[test]
 fn test_error_handling() {
     struct ErrorHandling {
@@ -440,6 +765,42 @@ fn test_error_handling() {
             input: "foobar".to_string(),
             expected: "Identifier not found: foobar".to_string(),
         },
+        ErrorHandling {
+            input: "5 / 0".to_string(),
+            expected: "Division by zero".to_string(),
+        },
+        ErrorHandling {
+            input: "rational(1, 0)".to_string(),
+            expected: "Division by zero".to_string(),
+        },
+        ErrorHandling {
+            input: "rational(1, 3) / 0".to_string(),
+            expected: "Division by zero".to_string(),
+        },
+        ErrorHandling {
+            input: "rational(1)".to_string(),
+            expected: "Wrong number of arguments to `rational`: got 1, want 2".to_string(),
+        },
+        ErrorHandling {
+            input: "fn(x) { x }()".to_string(),
+            expected: "Wrong number of arguments: got 0, want 1".to_string(),
+        },
+        ErrorHandling {
+            input: "let f = fn(a, b) { a }; f(1)".to_string(),
+            expected: "Wrong number of arguments: got 1, want 2".to_string(),
+        },
+        ErrorHandling {
+            input: "fn() { 1 }(2)".to_string(),
+            expected: "Wrong number of arguments: got 1, want 0".to_string(),
+        },
+        ErrorHandling {
+            input: "rational(true, 2)".to_string(),
+            expected: "Argument to `rational` must be Integer, got Boolean".to_string(),
+        },
+        ErrorHandling {
+            input: "rational(1, 2) + true".to_string(),
+            expected: "Type mismatch: Rational + Boolean".to_string(),
+        },
     ];
 
     for tt in tests {
@@ -483,9 +844,128 @@ fn test_boolean_object(object: Object, expected: bool) {
 
 fn test_null_object(object: Object) {
     let obj_type = &object.object_type();
-    let inspect = &object.inspect();
-    let obj: Null = match object.downcast() {
+    let _obj: Null = match object.downcast() {
         Some(x) => x,
         None => panic!("Could not downcast {:?} to Null", obj_type),
     };
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize() {
+    let tests = vec![
+        ("5", Ok("5")),
+        ("-5", Ok("-5")),
+        ("9223372036854775807 + 1", Ok("9223372036854775808")),
+        ("-9223372036854775807 - 2", Ok("-9223372036854775809")),
+        ("1 < 2", Ok("true")),
+        ("if (false) { 1 }", Ok("null")),
+        (
+            "9223372036854775807 * 9223372036854775807 * 9223372036854775807",
+            Err("Integer 784637716923335095224261902710254454442933591094742482943 is too large to serialize"),
+        ),
+        ("rational(1, 2)", Ok("{\"numerator\":1,\"denominator\":2}")),
+        ("rational(2, -6)", Ok("{\"numerator\":-1,\"denominator\":3}")),
+        ("fn(x) { x }", Err("Cannot serialize a value of type Function")),
+        ("rational", Err("Cannot serialize a value of type Builtin")),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        let object = evaluate(input.to_string());
+        let actual = serde_json::to_string(&object).map_err(|e| e.to_string());
+        assert_eq!(
+            actual,
+            expected.map(str::to_string).map_err(str::to_string),
+            "Test [{}] - wrong JSON for {}",
+            i,
+            input
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deserialize() {
+    use serde::de::value::I128Deserializer;
+    use serde::Deserialize;
+
+    let tests = vec![
+        ("5", Ok("5")),
+        ("-5", Ok("-5")),
+        ("18446744073709551615", Ok("18446744073709551615")),
+        ("true", Ok("true")),
+        ("null", Ok("null")),
+        (
+            "1.5",
+            Err("invalid type: floating point `1.5`, expected an integer, a rational, a boolean or null at line 1 column 3"),
+        ),
+        (
+            "\"crab\"",
+            Err("invalid type: string \"crab\", expected an integer, a rational, a boolean or null at line 1 column 6"),
+        ),
+        (
+            "[1]",
+            Err("invalid type: sequence, expected an integer, a rational, a boolean or null at line 1 column 1"),
+        ),
+        ("{\"numerator\": 2, \"denominator\": -4}", Ok("-1/2")),
+        ("{\"denominator\": 3, \"numerator\": 6}", Ok("2")),
+        (
+            "{\"numerator\": 1, \"denominator\": 0}",
+            Err("Division by zero at line 1 column 34"),
+        ),
+        (
+            "{\"numerator\": 1}",
+            Err("missing field `denominator` at line 1 column 16"),
+        ),
+        (
+            "{\"numerator\": 1, \"numerator\": 2, \"denominator\": 3}",
+            Err("duplicate field `numerator` at line 1 column 28"),
+        ),
+        (
+            "{\"numerator\": 1, \"den\": 2}",
+            Err("unknown field `den`, expected `numerator` or `denominator` at line 1 column 22"),
+        ),
+        (
+            "{\"numerator\": true, \"denominator\": 2}",
+            Err("Rational parts must be Integer, got Boolean at line 1 column 18"),
+        ),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        let actual = serde_json::from_str::<Object>(input)
+            .map(|o| o.inspect())
+            .map_err(|e| e.to_string());
+        assert_eq!(
+            actual,
+            expected.map(str::to_string).map_err(str::to_string),
+            "Test [{}] - wrong object for {}",
+            i,
+            input
+        );
+    }
+
+    // JSON has no integers beyond u64, but other formats may.
+    for value in [i128::MIN, -(1 << 64), 1 << 100, i128::MAX] {
+        let deserializer = I128Deserializer::<serde::de::value::Error>::new(value);
+        let object = Object::deserialize(deserializer).unwrap();
+        assert_eq!(
+            object.inspect(),
+            value.to_string(),
+            "Wrong object for {}",
+            value
+        );
+    }
+
+    // Values round-trip, and deserialized integers take the usual forms.
+    for input in [
+        "0",
+        "-9223372036854775808",
+        "9223372036854775808",
+        "false",
+        "rational(-1, 3)",
+        "rational(1, 9223372036854775807 * 2)",
+    ] {
+        let object = evaluate(input.to_string());
+        let json = serde_json::to_string(&object).unwrap();
+        let back: Object = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, object, "{} didn't round-trip", input);
+    }
+}
@@ -0,0 +1,132 @@
+// A small Wadler-style document algebra. The formatter describes the layout
+// it wants with these nodes and `render` decides, group by group, whether the
+// content fits on the current line or has to be broken.
+
+const INDENT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Text(String),
+    Line,        // a space, or a newline when the enclosing group breaks
+    SoftLine,    // nothing, or a newline when the enclosing group breaks
+    HardLine,    // always a newline; the enclosing groups break too
+    BreakParent, // prints nothing, but forces the enclosing groups to break
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+pub fn text(s: impl Into<String>) -> Doc {
+    Doc::Text(s.into())
+}
+
+pub fn nest(doc: Doc) -> Doc {
+    Doc::Nest(Box::new(doc))
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn join(docs: Vec<Doc>, separator: Doc) -> Doc {
+    let mut parts = Vec::new();
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i > 0 {
+            parts.push(separator.clone());
+        }
+        parts.push(doc);
+    }
+    Doc::Concat(parts)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                out.push_str(s);
+                column += s.chars().count();
+            }
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                newline(&mut out, indent);
+                column = indent;
+            }
+            Doc::BreakParent => {}
+            Doc::Nest(doc) => stack.push((indent + INDENT, mode, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            }
+            Doc::Group(doc) => {
+                let flat = mode == Mode::Flat
+                    || fits(width as isize - column as isize, (indent, doc), &stack);
+                let mode = if flat { Mode::Flat } else { Mode::Break };
+                stack.push((indent, mode, doc));
+            }
+        }
+    }
+
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out
+}
+
+// Whether `doc` laid out flat, plus whatever follows it up to the next line
+// break, fits in `remaining` columns.
+fn fits(mut remaining: isize, doc: (usize, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut work: Vec<(Mode, &Doc)> = vec![(Mode::Flat, doc.1)];
+
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let (mode, doc) = match work.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => remaining -= s.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::HardLine | Doc::BreakParent if mode == Mode::Flat => return false,
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::BreakParent => {}
+            Doc::Nest(doc) | Doc::Group(doc) => work.push((mode, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    work.push((mode, doc));
+                }
+            }
+        }
+    }
+}
+
+fn newline(out: &mut String, indent: usize) {
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    out.push('\n');
+    out.push_str(&" ".repeat(indent));
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use crate::formatter_utils::doc::{concat, group, join, nest, render, text, Doc};
+use crate::lexer_utils::lexer::{matching_delimiters, Lexer};
+use crate::lexer_utils::token::{Comment, Position, Token, TokenType};
+use crate::parser_utils::ast::{
+    BlockStatement, Expression, ExpressionStatement, Node, Program, Statement,
+};
+use crate::parser_utils::parser::{precedence_table, Parser, Precedence};
+
+pub const LINE_WIDTH: usize = 80;
+
+// Formats Crab source, keeping its comments. Returns the parser errors if the
+// source doesn't parse.
+pub fn format_source(input: &str) -> Result<String, Vec<String>> {
+    let mut l = Lexer::new(input.to_string());
+    let tokens: Vec<Token> = l.by_ref().collect();
+    let comments = std::mem::take(&mut l.comments);
+
+    let mut p = Parser::new(Lexer::new(input.to_string()));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return Err(p.errors().clone());
+    }
+
+    let mut f = Formatter::new(tokens, comments);
+    Ok(f.format(&program))
+}
+
+// Formats a syntax tree on its own, with no source to take comments from.
+pub fn format_program(program: &Program) -> String {
+    Formatter::new(Vec::new(), Vec::new()).format(program)
+}
+
+struct Formatter {
+    tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    block_ends: HashMap<Position, Position>, // `{` position -> matching `}`
+    precedences: HashMap<TokenType, Precedence>,
+}
+impl Formatter {
+    fn new(tokens: Vec<Token>, comments: Vec<Comment>) -> Formatter {
+        let block_ends = matching_delimiters(&tokens);
+        Formatter {
+            tokens,
+            comments,
+            next_comment: 0,
+            block_ends,
+            precedences: precedence_table(),
+        }
+    }
+
+    fn format(&mut self, program: &Program) -> String {
+        let end = Position::new(usize::MAX, usize::MAX);
+        let doc = self.statements(&program.statements, end, false);
+        let mut out = render(&doc, LINE_WIDTH);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+
+    fn statements(&mut self, nodes: &[Node], end: Position, in_block: bool) -> Doc {
+        let mut parts = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let start = node.get_token().position;
+            self.comments_before(start, &mut parts);
+            if !parts.is_empty() {
+                self.separate(start, &mut parts);
+            }
+            let next = nodes.get(i + 1);
+            parts.push(self.statement(node, next, in_block));
+        }
+        self.comments_before(end, &mut parts);
+        concat(parts)
+    }
+
+    // Moves every pending comment that starts before `position` into `parts`.
+    // Comments that trailed code stay on the end of that line.
+    fn comments_before(&mut self, position: Position, parts: &mut Vec<Doc>) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.position >= position {
+                break;
+            }
+            let comment = comment.clone();
+            if comment.own_line || parts.is_empty() {
+                if !parts.is_empty() {
+                    self.separate(comment.position, parts);
+                }
+                parts.push(text(comment.text));
+            } else {
+                parts.push(text(format!(" {}", comment.text)));
+            }
+            parts.push(Doc::BreakParent);
+            self.next_comment += 1;
+        }
+    }
+
+    // A line break between two items, keeping at most one blank line from
+    // the source.
+    fn separate(&self, position: Position, parts: &mut Vec<Doc>) {
+        parts.push(Doc::HardLine);
+        if self.blank_line_before(position) {
+            parts.push(Doc::HardLine);
+        }
+    }
+
+    fn blank_line_before(&self, position: Position) -> bool {
+        let i = self.tokens.partition_point(|t| t.position < position);
+        let token_line = i.checked_sub(1).map(|i| self.tokens[i].position.line);
+        let comment_line = self
+            .comments
+            .iter()
+            .take_while(|c| c.position < position)
+            .last()
+            .map(|c| c.position.line);
+        match token_line.max(comment_line) {
+            Some(line) => position.line > line + 1,
+            None => false,
+        }
+    }
+
+    fn statement(&mut self, node: &Node, next: Option<&Node>, in_block: bool) -> Doc {
+        let stmt = match node {
+            Node::Statement(stmt) => stmt,
+            Node::Expression(expr) => return self.expression(expr),
+        };
+        match stmt {
+            Statement::LetStatement(ls) => concat(vec![
+                text(format!("let {} = ", ls.name.value)),
+                self.expression(&ls.value),
+                text(";"),
+            ]),
+            Statement::ReturnStatement(rs) => concat(vec![
+                text("return "),
+                self.expression(&rs.return_value),
+                text(";"),
+            ]),
+            Statement::ExpressionStatement(es) => {
+                let expr = self.expression(&es.expression);
+                if needs_semicolon(es, next, in_block) {
+                    concat(vec![expr, text(";")])
+                } else {
+                    expr
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Doc {
+        match expr {
+            Expression::Identifier(id) => text(id.value.clone()),
+            Expression::IntegerLiteral(il) => {
+                // Keep the literal as written (`0xFF`, `1_000`) when we have it.
+                if il.token.token_type == TokenType::INT && !il.token.literal.is_empty() {
+                    text(il.token.literal.clone())
+                } else {
+                    text(il.value.to_string())
+                }
+            }
+            Expression::BooleanExpression(b) => text(b.value.to_string()),
+            Expression::PrefixExpression(pe) => concat(vec![
+                text(pe.operator.clone()),
+                self.operand(&pe.right, &Precedence::PREFIX, true),
+            ]),
+            Expression::InfixExpression(ie) => {
+                let precedence = self.infix_precedence(&ie.token.token_type);
+                concat(vec![
+                    self.operand(&ie.left, &precedence, false),
+                    text(format!(" {} ", ie.operator)),
+                    self.operand(&ie.right, &precedence, true),
+                ])
+            }
+            Expression::IfExpression(ie) => {
+                let mut parts = vec![
+                    text("if ("),
+                    self.expression(&ie.condition),
+                    text(") "),
+                    self.block(&ie.consequence),
+                ];
+                if let Some(alternative) = &ie.alternative {
+                    parts.push(text(" else "));
+                    parts.push(self.block(alternative));
+                }
+                concat(parts)
+            }
+            Expression::FunctionLiteral(fl) => {
+                let parameters = fl
+                    .parameters
+                    .iter()
+                    .map(|p| text(p.value.clone()))
+                    .collect();
+                concat(vec![
+                    text("fn"),
+                    self.list(parameters),
+                    text(" "),
+                    self.block(&fl.body),
+                ])
+            }
+            Expression::CallExpression(ce) => {
+                let function = match ce.function.as_ref() {
+                    Expression::PrefixExpression(_) | Expression::InfixExpression(_) => {
+                        concat(vec![text("("), self.expression(&ce.function), text(")")])
+                    }
+                    function => self.expression(function),
+                };
+                let arguments = ce.arguments.iter().map(|a| self.expression(a)).collect();
+                concat(vec![function, self.list(arguments)])
+            }
+            Expression::BlockStatement(bs) => self.block(bs),
+        }
+    }
+
+    // An operand of a prefix or infix operator, parenthesized only when it
+    // binds more loosely than its parent. Operators are left-associative, so
+    // a right operand of equal precedence needs parentheses too.
+    fn operand(&mut self, expr: &Expression, parent: &Precedence, right: bool) -> Doc {
+        let precedence = match expr {
+            Expression::InfixExpression(ie) => self.infix_precedence(&ie.token.token_type),
+            Expression::PrefixExpression(_) => Precedence::PREFIX,
+            _ => Precedence::CALL,
+        };
+        let doc = self.expression(expr);
+        if precedence < *parent || (right && precedence == *parent) {
+            concat(vec![text("("), doc, text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn infix_precedence(&self, t: &TokenType) -> Precedence {
+        match self.precedences.get(t) {
+            Some(p) => p.clone(),
+            None => Precedence::LOWEST,
+        }
+    }
+
+    // `(a, b, c)`, or one item per line when that doesn't fit.
+    fn list(&mut self, items: Vec<Doc>) -> Doc {
+        if items.is_empty() {
+            return text("()");
+        }
+        group(concat(vec![
+            text("("),
+            nest(concat(vec![
+                Doc::SoftLine,
+                join(items, concat(vec![text(","), Doc::Line])),
+            ])),
+            Doc::SoftLine,
+            text(")"),
+        ]))
+    }
+
+    // `{ x }` when the block fits on one line, otherwise one statement per
+    // line.
+    fn block(&mut self, block: &BlockStatement) -> Doc {
+        let end = match self.block_ends.get(&block.token.position) {
+            Some(end) => *end,
+            None => Position::default(),
+        };
+        let body = self.statements(&block.statements, end, true);
+        if matches!(&body, Doc::Concat(parts) if parts.is_empty()) {
+            return text("{}");
+        }
+        group(concat(vec![
+            text("{"),
+            nest(concat(vec![Doc::Line, body])),
+            Doc::Line,
+            text("}"),
+        ]))
+    }
+}
+
+// Expression statements end in `;` unless they give a block its value. An
+// `if` may also drop it when a `let` or `return` follows, since nothing could
+// be mistaken for a continuation of the expression.
+fn needs_semicolon(stmt: &ExpressionStatement, next: Option<&Node>, in_block: bool) -> bool {
+    let is_if = matches!(stmt.expression.as_ref(), Expression::IfExpression(_));
+    match next {
+        None => !in_block && !is_if,
+        Some(Node::Statement(Statement::ExpressionStatement(_))) => true,
+        Some(_) => !is_if,
+    }
+}
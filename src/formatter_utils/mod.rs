@@ -0,0 +1,4 @@
+pub mod doc;
+pub mod formatter;
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,470 @@
+use proptest::prelude::*;
+
+use crate::formatter_utils::formatter::{format_program, format_source};
+use crate::lexer_utils::lexer::Lexer;
+use crate::lexer_utils::token::{Position, Token, TokenType};
+use crate::parser_utils::ast::{
+    BlockStatement, BooleanExpression, CallExpression, Expression, ExpressionStatement,
+    FunctionLiteral, Identifier, IfExpression, InfixExpression, IntegerLiteral, LetStatement, Node,
+    PrefixExpression, Program, ReturnStatement, Statement,
+};
+use crate::parser_utils::parser::{precedence_table, Parser};
+
+fn format(input: &str) -> String {
+    match format_source(input) {
+        Ok(formatted) => formatted,
+        Err(errors) => panic!("Test [{}] - Parser errors: {:?}", input, errors),
+    }
+}
+
+fn program_string(input: &str) -> String {
+    let l = Lexer::new(input.to_string());
+    let mut p = Parser::new(l);
+    p.parse_program().string()
+}
+
+#[test]
+fn test_format_statements() {
+    let tests = [
+        ("let x=5", "let x = 5;\n"),
+        ("return   x", "return x;\n"),
+        ("5 + 5", "5 + 5;\n"),
+        (
+            "let y = 0xFF; let z = 1_000;",
+            "let y = 0xFF;\nlet z = 1_000;\n",
+        ),
+        ("add(1,2)", "add(1, 2);\n"),
+        ("fn(){}", "fn() {};\n"),
+        ("let f = fn(x) { x * 2; };", "let f = fn(x) { x * 2 };\n"),
+        (
+            "if (x < y) { x } else { y }; let a = 1;",
+            "if (x < y) { x } else { y }\nlet a = 1;\n",
+        ),
+        ("if (x) { 1 }; -1", "if (x) { 1 };\n-1;\n"),
+        ("", ""),
+    ];
+
+    for tt in tests {
+        assert_eq!(format(tt.0), tt.1, "Test [{}] - Wrong output", tt.0);
+    }
+}
+
+#[test]
+fn test_format_parentheses() {
+    let tests = [
+        ("((1 + 2)) * 3", "(1 + 2) * 3;\n"),
+        ("1 + (2 * 3)", "1 + 2 * 3;\n"),
+        ("(1 + 2) + 3", "1 + 2 + 3;\n"),
+        ("1 + (2 + 3)", "1 + (2 + 3);\n"),
+        ("1 - (2 - 3)", "1 - (2 - 3);\n"),
+        ("-(1 + 2)", "-(1 + 2);\n"),
+        ("-(-x)", "-(-x);\n"),
+        ("- -x", "-(-x);\n"),
+        ("!(a == b)", "!(a == b);\n"),
+        ("(a < b) == (c > d)", "a < b == c > d;\n"),
+        ("a == (b == c)", "a == (b == c);\n"),
+        ("fn(x) { x }(5)", "fn(x) { x }(5);\n"),
+    ];
+
+    for tt in tests {
+        assert_eq!(format(tt.0), tt.1, "Test [{}] - Wrong output", tt.0);
+    }
+}
+
+#[test]
+fn test_format_wraps_long_lines() {
+    let input = "let z = callSomethingWithAVeryLongName(argumentNumberOne, argumentNumberTwo, argumentNumberThree);
+let big = fn(first, second, third) { let sum = first + second + third; sum * 2 };";
+    let expected = "let z = callSomethingWithAVeryLongName(
+    argumentNumberOne,
+    argumentNumberTwo,
+    argumentNumberThree
+);
+let big = fn(first, second, third) {
+    let sum = first + second + third;
+    sum * 2
+};
+";
+    assert_eq!(format(input), expected, "Wrong output");
+}
+
+#[test]
+fn test_format_preserves_comments() {
+    let input = "// Adds two numbers.
+let add = fn(a, b) { return a + b; };
+
+
+let x = 10;   // ten
+let f = fn(x) {
+  // doubles
+  x * 2 // the result
+};
+// end";
+    let expected = "// Adds two numbers.
+let add = fn(a, b) { return a + b; };
+
+let x = 10; // ten
+let f = fn(x) {
+    // doubles
+    x * 2 // the result
+};
+// end
+";
+    assert_eq!(format(input), expected, "Wrong output");
+}
+
+#[test]
+fn test_format_is_idempotent() {
+    let tests = [
+        "let add = fn(a, b) { return a + b; }; let result = add(10, 15);",
+        "if (a > b) { let c = a; c } else { b }; let x = add(add(1, 2), fn(y) { y }(3));",
+        "let newAdder = fn(x) { fn(y) { x + y }; }; // closure\n\n\nnewAdder(2)(3)",
+        "let z = callSomethingWithAVeryLongName(argumentNumberOne, argumentNumberTwo, argumentNumberThree(4, 5, 6));",
+        "let f = fn(x) {\n// only a comment\n};\n// trailing",
+        "-(-(1 - (2 - 3))) * !(true == false)",
+    ];
+
+    for tt in tests {
+        let once = format(tt);
+        let twice = format(&once);
+        assert_eq!(once, twice, "Test [{}] - Formatting is not idempotent", tt);
+    }
+}
+
+#[test]
+fn test_format_preserves_meaning() {
+    let tests = [
+        "let add = fn(a, b) { return a + b; }; let result = add(10, 15);",
+        "if (a > b) { let c = a; c } else { b }; -1",
+        "a + b * c + d / e - f",
+        "(5 + 5) * 2 == -(1 - 2) < 3",
+        "add(a + b + c * d / f + g)",
+        "fn(x) { x; }(5)",
+    ];
+
+    for tt in tests {
+        let formatted = format(tt);
+        assert_eq!(
+            program_string(&formatted),
+            program_string(tt),
+            "Test [{}] - Formatted program differs",
+            tt
+        );
+    }
+}
+
+#[test]
+fn test_format_reports_parse_errors() {
+    let errors = format_source("let = 5;").unwrap_err();
+    assert_eq!(
+        errors,
+        vec!["1:5: Expected next token to be IDENT, got ASSIGN instead"],
+        "Parser errors are wrong"
+    );
+}
+
+// Round-trip properties: random syntax trees are printed with the formatter,
+// parsed back, and must come out structurally identical. Token positions are
+// the only thing allowed to differ.
+
+fn tok(token_type: TokenType, literal: &str) -> Token {
+    Token::new_token(token_type, literal.to_string())
+}
+
+fn identifier(name: &str) -> Identifier {
+    Identifier {
+        token: tok(TokenType::IDENT, name),
+        value: name.to_string(),
+    }
+}
+
+fn block(statements: Vec<Node>) -> BlockStatement {
+    BlockStatement {
+        token: tok(TokenType::LBRACE, "{"),
+        statements,
+    }
+}
+
+const INFIX_OPERATORS: [(&str, TokenType); 8] = [
+    ("+", TokenType::PLUS),
+    ("-", TokenType::MINUS),
+    ("*", TokenType::ASTERISK),
+    ("/", TokenType::SLASH),
+    ("<", TokenType::LT),
+    (">", TokenType::GT),
+    ("==", TokenType::EQ),
+    ("!=", TokenType::NOTEQ),
+];
+
+fn arb_identifier() -> impl Strategy<Value = Identifier> {
+    prop::sample::select(vec!["a", "b", "x", "foo", "bar_2"]).prop_map(identifier)
+}
+
+// `hex` lets integer literals keep a spelling their value doesn't print as.
+fn arb_leaf(hex: bool) -> impl Strategy<Value = Expression> {
+    prop_oneof![
+        arb_identifier().prop_map(Expression::Identifier),
+        (0..=i64::MAX, any::<bool>()).prop_map(move |(value, spell_hex)| {
+            let literal = if hex && spell_hex {
+                format!("0x{:X}", value)
+            } else {
+                value.to_string()
+            };
+            Expression::IntegerLiteral(IntegerLiteral {
+                token: tok(TokenType::INT, &literal),
+                value,
+            })
+        }),
+        any::<bool>().prop_map(|value| {
+            Expression::BooleanExpression(BooleanExpression {
+                token: match value {
+                    true => tok(TokenType::TRUE, "true"),
+                    false => tok(TokenType::FALSE, "false"),
+                },
+                value,
+            })
+        }),
+    ]
+}
+
+fn arb_expression() -> impl Strategy<Value = Expression> {
+    arb_leaf(true).prop_recursive(4, 48, 3, |inner| {
+        prop_oneof![
+            arb_operation(inner.clone()),
+            (
+                inner.clone(),
+                arb_block(inner.clone()),
+                prop::option::of(arb_block(inner.clone()))
+            )
+                .prop_map(|(condition, consequence, alternative)| {
+                    Expression::IfExpression(IfExpression {
+                        token: tok(TokenType::IF, "if"),
+                        condition: Box::new(condition),
+                        consequence,
+                        alternative,
+                    })
+                }),
+            (
+                prop::collection::vec(arb_identifier(), 0..3),
+                arb_block(inner)
+            )
+                .prop_map(|(parameters, body)| {
+                    Expression::FunctionLiteral(FunctionLiteral {
+                        token: tok(TokenType::FUNCTION, "fn"),
+                        parameters,
+                        body,
+                    })
+                }),
+        ]
+    })
+}
+
+// Expressions without blocks, which `Expression::string` prints as source.
+fn arb_operator_expression() -> impl Strategy<Value = Expression> {
+    arb_leaf(false).prop_recursive(4, 48, 3, |inner| arb_operation(inner.boxed()))
+}
+
+// Prefix, infix and call expressions over `inner`.
+fn arb_operation(inner: BoxedStrategy<Expression>) -> impl Strategy<Value = Expression> {
+    prop_oneof![
+        (
+            prop::sample::select(vec![("-", TokenType::MINUS), ("!", TokenType::BANG)]),
+            inner.clone()
+        )
+            .prop_map(|((operator, t), right)| {
+                Expression::PrefixExpression(PrefixExpression {
+                    token: tok(t, operator),
+                    operator: operator.to_string(),
+                    right: Box::new(right),
+                })
+            }),
+        (
+            inner.clone(),
+            prop::sample::select(INFIX_OPERATORS.to_vec()),
+            inner.clone()
+        )
+            .prop_map(|(left, (operator, t), right)| {
+                Expression::InfixExpression(InfixExpression {
+                    token: tok(t, operator),
+                    left: Box::new(left),
+                    operator: operator.to_string(),
+                    right: Box::new(right),
+                })
+            }),
+        (inner.clone(), prop::collection::vec(inner.clone(), 0..3)).prop_map(
+            |(function, arguments)| {
+                Expression::CallExpression(CallExpression {
+                    token: tok(TokenType::LPAREN, "("),
+                    function: Box::new(function),
+                    arguments,
+                })
+            }
+        ),
+    ]
+}
+
+fn arb_block(expr: BoxedStrategy<Expression>) -> impl Strategy<Value = BlockStatement> {
+    prop::collection::vec(arb_statement(expr), 0..3).prop_map(block)
+}
+
+fn arb_statement(expr: BoxedStrategy<Expression>) -> impl Strategy<Value = Node> {
+    prop_oneof![
+        (arb_identifier(), expr.clone()).prop_map(|(name, value)| {
+            Node::Statement(Statement::LetStatement(LetStatement {
+                token: tok(TokenType::LET, "let"),
+                name,
+                value,
+            }))
+        }),
+        expr.clone().prop_map(|return_value| {
+            Node::Statement(Statement::ReturnStatement(ReturnStatement {
+                token: tok(TokenType::RETURN, "return"),
+                return_value,
+            }))
+        }),
+        expr.prop_map(|expression| {
+            Node::Statement(Statement::ExpressionStatement(ExpressionStatement {
+                token: first_token(&expression),
+                expression: Box::new(expression),
+            }))
+        }),
+    ]
+}
+
+fn arb_program() -> impl Strategy<Value = Vec<Node>> {
+    prop::collection::vec(arb_statement(arb_expression().boxed()), 0..5)
+}
+
+// The parser gives an expression statement the first token of its source,
+// which is `(` when the printer has to parenthesize the leftmost operand.
+fn first_token(expr: &Expression) -> Token {
+    match expr {
+        Expression::Identifier(e) => e.token.clone(),
+        Expression::IntegerLiteral(e) => e.token.clone(),
+        Expression::BooleanExpression(e) => e.token.clone(),
+        Expression::PrefixExpression(e) => e.token.clone(),
+        Expression::IfExpression(e) => e.token.clone(),
+        Expression::FunctionLiteral(e) => e.token.clone(),
+        Expression::BlockStatement(e) => e.token.clone(),
+        Expression::InfixExpression(e) => {
+            let table = precedence_table();
+            let looser = match e.left.as_ref() {
+                Expression::InfixExpression(left) => {
+                    table[&left.token.token_type] < table[&e.token.token_type]
+                }
+                _ => false,
+            };
+            if looser {
+                tok(TokenType::LPAREN, "(")
+            } else {
+                first_token(&e.left)
+            }
+        }
+        Expression::CallExpression(e) => match e.function.as_ref() {
+            Expression::PrefixExpression(_) | Expression::InfixExpression(_) => {
+                tok(TokenType::LPAREN, "(")
+            }
+            function => first_token(function),
+        },
+    }
+}
+
+fn strip_node(node: &mut Node) {
+    match node {
+        Node::Statement(Statement::LetStatement(s)) => {
+            s.token.position = Position::default();
+            s.name.token.position = Position::default();
+            strip_expression(&mut s.value);
+        }
+        Node::Statement(Statement::ReturnStatement(s)) => {
+            s.token.position = Position::default();
+            strip_expression(&mut s.return_value);
+        }
+        Node::Statement(Statement::ExpressionStatement(s)) => {
+            s.token.position = Position::default();
+            strip_expression(&mut s.expression);
+        }
+        Node::Expression(e) => strip_expression(e),
+    }
+}
+
+fn strip_block(block: &mut BlockStatement) {
+    block.token.position = Position::default();
+    block.statements.iter_mut().for_each(strip_node);
+}
+
+fn strip_expression(expr: &mut Expression) {
+    match expr {
+        Expression::Identifier(e) => e.token.position = Position::default(),
+        Expression::IntegerLiteral(e) => e.token.position = Position::default(),
+        Expression::BooleanExpression(e) => e.token.position = Position::default(),
+        Expression::PrefixExpression(e) => {
+            e.token.position = Position::default();
+            strip_expression(&mut e.right);
+        }
+        Expression::InfixExpression(e) => {
+            e.token.position = Position::default();
+            strip_expression(&mut e.left);
+            strip_expression(&mut e.right);
+        }
+        Expression::BlockStatement(e) => strip_block(e),
+        Expression::IfExpression(e) => {
+            e.token.position = Position::default();
+            strip_expression(&mut e.condition);
+            strip_block(&mut e.consequence);
+            if let Some(alternative) = &mut e.alternative {
+                strip_block(alternative);
+            }
+        }
+        Expression::FunctionLiteral(e) => {
+            e.token.position = Position::default();
+            e.parameters
+                .iter_mut()
+                .for_each(|p| p.token.position = Position::default());
+            strip_block(&mut e.body);
+        }
+        Expression::CallExpression(e) => {
+            e.token.position = Position::default();
+            strip_expression(&mut e.function);
+            e.arguments.iter_mut().for_each(strip_expression);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_printed_program_reparses(statements in arb_program()) {
+        let printed = format_program(&Program { statements: statements.clone() });
+
+        let l = Lexer::new(printed.clone());
+        let mut p = Parser::new(l);
+        let mut reparsed = p.parse_program();
+        prop_assert!(p.errors().is_empty(), "Parser errors {:?} in:\n{}", p.errors(), printed);
+
+        reparsed.statements.iter_mut().for_each(strip_node);
+        prop_assert_eq!(&reparsed.statements, &statements, "Round trip differs for:\n{}", printed);
+    }
+
+    // `Program::string` is a debug printer: it drops `let` and runs statements
+    // together, so only the expressions it prints are meant to reparse.
+    #[test]
+    fn test_expression_string_reparses(expression in arb_operator_expression()) {
+        let printed = expression.string();
+
+        let l = Lexer::new(printed.clone());
+        let mut p = Parser::new(l);
+        let mut reparsed = p.parse_program();
+        prop_assert!(p.errors().is_empty(), "Parser errors {:?} in:\n{}", p.errors(), printed);
+        prop_assert_eq!(reparsed.statements.len(), 1, "Statement count is wrong for:\n{}", printed);
+
+        let mut actual = reparsed.statements.remove(0).get_statement_expr().expression.clone();
+        strip_expression(&mut actual);
+        prop_assert_eq!(*actual, expression, "Round trip differs for:\n{}", printed);
+    }
+
+    #[test]
+    fn test_printed_program_is_canonical(statements in arb_program()) {
+        let printed = format_program(&Program { statements });
+        prop_assert_eq!(format(&printed), printed);
+    }
+}
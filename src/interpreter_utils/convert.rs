@@ -0,0 +1,92 @@
+// Conversions between Rust values and Crab objects, so host code can write
+// `42.into_crab()` and `i64::from_crab(value)` instead of building and
+// matching `Object` variants by hand.
+
+use crate::evaluator_utils::object::{Boolean, Integer, Null, Object};
+
+/// A Rust value that has a Crab counterpart.
+pub trait IntoCrab {
+    fn into_crab(self) -> Object;
+}
+
+/// A Rust value that can be read back from a Crab object. Fails with a
+/// message naming the expected and the actual type.
+pub trait FromCrab: Sized {
+    fn from_crab(value: Object) -> Result<Self, String>;
+}
+
+impl IntoCrab for Object {
+    fn into_crab(self) -> Object {
+        self
+    }
+}
+impl FromCrab for Object {
+    fn from_crab(value: Object) -> Result<Object, String> {
+        Ok(value)
+    }
+}
+
+impl IntoCrab for i64 {
+    fn into_crab(self) -> Object {
+        Object::Integer(Integer { value: self })
+    }
+}
+impl FromCrab for i64 {
+    fn from_crab(value: Object) -> Result<i64, String> {
+        match value {
+            Object::Integer(i) => Ok(i.value),
+            Object::BigInteger(i) => Err(format!("integer {} is out of range", i.value)),
+            value => Err(mismatch("Integer", &value)),
+        }
+    }
+}
+
+impl IntoCrab for bool {
+    fn into_crab(self) -> Object {
+        Object::Boolean(Boolean { value: self })
+    }
+}
+impl FromCrab for bool {
+    fn from_crab(value: Object) -> Result<bool, String> {
+        match value {
+            Object::Boolean(b) => Ok(b.value),
+            value => Err(mismatch("Boolean", &value)),
+        }
+    }
+}
+
+impl IntoCrab for () {
+    fn into_crab(self) -> Object {
+        Object::Null(Null {})
+    }
+}
+impl FromCrab for () {
+    fn from_crab(value: Object) -> Result<(), String> {
+        match value {
+            Object::Null(_) => Ok(()),
+            value => Err(mismatch("Null", &value)),
+        }
+    }
+}
+
+// `None` is `null`, and `null` is `None`.
+impl<T: IntoCrab> IntoCrab for Option<T> {
+    fn into_crab(self) -> Object {
+        match self {
+            Some(value) => value.into_crab(),
+            None => Object::Null(Null {}),
+        }
+    }
+}
+impl<T: FromCrab> FromCrab for Option<T> {
+    fn from_crab(value: Object) -> Result<Option<T>, String> {
+        match value {
+            Object::Null(_) => Ok(None),
+            value => T::from_crab(value).map(Some),
+        }
+    }
+}
+
+fn mismatch(expected: &str, actual: &Object) -> String {
+    format!("expected {}, got {:?}", expected, actual.object_type())
+}
@@ -0,0 +1,192 @@
+use crate::evaluator_utils::environment::Environment;
+use crate::evaluator_utils::evaluator::eval;
+use crate::evaluator_utils::object::{Arity, Native, Object};
+use crate::evaluator_utils::prelude;
+use crate::evaluator_utils::runtime::{CancelToken, Interrupt, Limits, Output};
+use crate::interpreter_utils::convert::IntoCrab;
+use crate::lexer_utils::lexer::Lexer;
+use crate::parser_utils::parser::Parser;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A value produced by or handed to Crab code.
+pub type Value = Object;
+
+/// Why a piece of source could not be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrabError {
+    /// The source doesn't parse. Each message starts with `line:column: `.
+    Parse(Vec<String>),
+    /// Evaluation stopped with an error, e.g. `Division by zero`.
+    Runtime(String),
+    /// Evaluation ran out of one of its [`Limits`].
+    Interrupted(Interrupt),
+}
+
+impl fmt::Display for CrabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrabError::Parse(errors) => write!(f, "parse error: {}", errors.join("; ")),
+            CrabError::Runtime(message) => write!(f, "runtime error: {}", message),
+            CrabError::Interrupted(interrupt) => write!(f, "runtime error: {}", interrupt),
+        }
+    }
+}
+
+impl std::error::Error for CrabError {}
+
+/// An embedded Crab session. Globals set by the host and bindings made by
+/// evaluated code live in one environment, which starts with the prelude and
+/// persists across calls to [`Interpreter::eval_str`].
+///
+/// ```
+/// use interpreter::Interpreter;
+///
+/// let mut interp = Interpreter::new();
+/// interp.eval_str("let double = fn(x) { x * 2 };").unwrap();
+/// let value = interp.eval_str("double(21)").unwrap();
+/// assert_eq!(value.inspect(), "42");
+/// ```
+pub struct Interpreter {
+    env: Environment,
+}
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            env: prelude::environment(),
+        }
+    }
+
+    /// Parses and evaluates `src`, returning the value of its last statement.
+    /// Bindings it makes stay visible to later calls, also when it fails.
+    pub fn eval_str(&mut self, src: &str) -> Result<Value, CrabError> {
+        let mut p = Parser::new(Lexer::new(src.to_string()));
+        let program = p.parse_program();
+        if !p.errors().is_empty() {
+            return Err(CrabError::Parse(p.errors().clone()));
+        }
+        let runtime = self.env.runtime().clone();
+        runtime.reset();
+        match eval(&program.statements, &mut self.env) {
+            Object::Error(e) => match runtime.interrupt() {
+                Some(interrupt) => Err(CrabError::Interrupted(interrupt)),
+                None => Err(CrabError::Runtime(e.message)),
+            },
+            value => Ok(value),
+        }
+    }
+
+    /// Binds `name` for the code evaluated from now on, replacing any value
+    /// it had.
+    pub fn set_global(&mut self, name: &str, value: impl IntoCrab) {
+        self.env.set(name.to_string(), value.into_crab());
+    }
+
+    /// Binds `name` to a Rust function that scripts can call like any other,
+    /// and pass to functions such as `compose`. Calls with a number of
+    /// arguments `arity` doesn't accept fail before `function` runs; an `Err`
+    /// it returns becomes a runtime error prefixed with `name`.
+    ///
+    /// ```
+    /// use interpreter::{Arity, FromCrab, Interpreter, IntoCrab};
+    ///
+    /// let mut interp = Interpreter::new();
+    /// interp.register_fn("sum", Arity::AtLeast(1), |args| {
+    ///     let mut total = 0;
+    ///     for arg in args {
+    ///         total += i64::from_crab(arg)?;
+    ///     }
+    ///     Ok(total.into_crab())
+    /// });
+    /// assert_eq!(interp.eval_str("sum(1, 2, 3)").unwrap().inspect(), "6");
+    /// ```
+    pub fn register_fn<F>(&mut self, name: &str, arity: impl Into<Arity>, function: F)
+    where
+        F: Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    {
+        let native = Native {
+            name: name.to_string(),
+            arity: arity.into(),
+            function: Arc::new(function),
+        };
+        self.set_global(name, Object::Native(native));
+    }
+
+    /// Sends what scripts print with `puts` to `output` instead of stdout.
+    /// Functions defined earlier print there too.
+    ///
+    /// ```
+    /// use interpreter::{Interpreter, OutputBuffer};
+    ///
+    /// let mut interp = Interpreter::new();
+    /// let printed = OutputBuffer::new();
+    /// interp.set_output(printed.clone());
+    /// interp.eval_str("puts(1, 1 + 1)").unwrap();
+    /// assert_eq!(printed.take(), "1\n2\n");
+    /// ```
+    pub fn set_output(&mut self, output: impl Output + 'static) {
+        self.env.runtime().set_output(Box::new(output));
+    }
+
+    /// Bounds every following call to [`Interpreter::eval_str`]. Each call
+    /// starts with a fresh budget; one that runs out fails with
+    /// [`CrabError::Interrupted`] instead of hanging or overflowing the stack.
+    ///
+    /// ```
+    /// use interpreter::{CrabError, Interpreter, Limits};
+    ///
+    /// let mut interp = Interpreter::new();
+    /// interp.set_limits(Limits {
+    ///     max_steps: Some(1000),
+    ///     ..Limits::default()
+    /// });
+    /// let result = interp.eval_str("let w = fn(f) { f(f) }; w(w)");
+    /// assert!(matches!(result, Err(CrabError::Interrupted(_))));
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.env.runtime().set_limits(limits);
+    }
+
+    /// Stops evaluations still running at `deadline` with
+    /// [`Interrupt::Timeout`]. The deadline applies to every following call
+    /// until it is changed or cleared with `None`.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.env.runtime().set_deadline(deadline);
+    }
+
+    /// A handle that stops evaluation from any thread. Once cancelled, every
+    /// evaluation fails with [`Interrupt::Cancelled`] until a fresh token is
+    /// installed with [`Interpreter::set_cancel_token`].
+    ///
+    /// ```
+    /// use interpreter::{CancelToken, CrabError, Interpreter, Interrupt};
+    ///
+    /// let mut interp = Interpreter::new();
+    /// let token = interp.cancel_token();
+    /// std::thread::spawn(move || token.cancel()).join().unwrap();
+    /// let result = interp.eval_str("1 + 1");
+    /// assert_eq!(result, Err(CrabError::Interrupted(Interrupt::Cancelled)));
+    ///
+    /// interp.set_cancel_token(CancelToken::new());
+    /// assert_eq!(interp.eval_str("1 + 1").unwrap().inspect(), "2");
+    /// ```
+    pub fn cancel_token(&self) -> CancelToken {
+        self.env.runtime().cancel_token()
+    }
+
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.env.runtime().set_cancel_token(token);
+    }
+
+    /// The value bound to `name`, including prelude definitions.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.env.get(name).cloned()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
@@ -0,0 +1,4 @@
+pub mod convert;
+pub mod interpreter;
+#[cfg(test)]
+pub mod tests;
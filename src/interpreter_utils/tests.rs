@@ -0,0 +1,539 @@
+use crate::evaluator_utils::object::{Boolean, Integer, Object, ObjectType};
+use crate::{
+    apply_function, Arity, CancelToken, CrabError, FromCrab, Interpreter, Interrupt, IntoCrab,
+    Limits, OutputBuffer,
+};
+
+// Evaluates test `i` and compares the inspected value, or the error, with
+// `expected`.
+fn assert_eval(
+    interp: &mut Interpreter,
+    i: usize,
+    input: &str,
+    expected: Result<String, CrabError>,
+) {
+    let actual = interp.eval_str(input).map(|v| v.inspect());
+    assert_eq!(
+        actual, expected,
+        "Test [{}] - wrong result for {}",
+        i, input
+    );
+}
+
+#[test]
+fn test_eval_str() {
+    let deep = format!("{}1", "-".repeat(200_000));
+    let tests = vec![
+        ("1 + 2 * 3", Ok("7".to_string())),
+        ("let x = 5; x * x", Ok("25".to_string())),
+        ("max(3, 9)", Ok("9".to_string())),
+        ("if (false) { 1 }", Ok("null".to_string())),
+        (
+            "1 / 0",
+            Err(CrabError::Runtime("Division by zero".to_string())),
+        ),
+        (
+            "-true",
+            Err(CrabError::Runtime("Unknown operator: -Boolean".to_string())),
+        ),
+        (
+            &deep,
+            Err(CrabError::Parse(vec![
+                "1:101: Expression nested more than 100 levels deep".to_string(),
+            ])),
+        ),
+        (
+            "fn(x) { x }()",
+            Err(CrabError::Runtime(
+                "Wrong number of arguments: got 0, want 1".to_string(),
+            )),
+        ),
+        (
+            "let = 1;",
+            Err(CrabError::Parse(vec![
+                "1:5: Expected next token to be IDENT, got ASSIGN instead".to_string(),
+            ])),
+        ),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        assert_eval(&mut Interpreter::new(), i, input, expected);
+    }
+}
+
+#[test]
+fn test_bindings_persist() {
+    let mut interp = Interpreter::new();
+    interp.eval_str("let add = fn(a, b) { a + b };").unwrap();
+    interp.eval_str("let three = add(1, 2);").unwrap();
+    let value = interp.eval_str("add(three, 4)").unwrap();
+    assert_eq!(value.inspect(), "7", "Bindings didn't persist across calls");
+
+    // A failing entry keeps what it bound before the error.
+    let result = interp.eval_str("let before = 1; 1 / 0; let after = 2;");
+    assert!(result.is_err(), "Division by zero didn't fail");
+    assert!(
+        interp.get_global("before").is_some(),
+        "Binding before the error was lost"
+    );
+    assert!(
+        interp.get_global("after").is_none(),
+        "Binding after the error was made"
+    );
+}
+
+#[test]
+fn test_globals() {
+    let mut interp = Interpreter::new();
+    interp.set_global("limit", Object::Integer(Integer { value: 10 }));
+    interp.set_global("verbose", Object::Boolean(Boolean { value: true }));
+    let tests = vec![
+        ("limit * 2", "20"),
+        ("if (verbose) { limit } else { 0 }", "10"),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        let value = interp.eval_str(input).unwrap();
+        assert_eq!(
+            value.inspect(),
+            expected,
+            "Test [{}] - wrong value for {}",
+            i,
+            input
+        );
+    }
+
+    // Setting a global again replaces it.
+    interp.set_global("limit", Object::Integer(Integer { value: 1 }));
+    assert_eq!(interp.eval_str("limit").unwrap().inspect(), "1");
+
+    interp.eval_str("let answer = 42;").unwrap();
+    let tests = vec![
+        ("answer", Some("42".to_string())),
+        (
+            "abs",
+            Some("fn (n) {\nif (n < 0) { (-n) } else { n }\n}".to_string()),
+        ),
+        ("missing", None),
+    ];
+    for (i, (name, expected)) in tests.into_iter().enumerate() {
+        let actual = interp.get_global(name).map(|v| v.inspect());
+        assert_eq!(actual, expected, "Test [{}] - wrong global {}", i, name);
+    }
+}
+
+#[test]
+fn test_error_display() {
+    let tests = vec![
+        (
+            CrabError::Runtime("Division by zero".to_string()),
+            "runtime error: Division by zero",
+        ),
+        (
+            CrabError::Parse(vec!["1:1: a".to_string(), "1:3: b".to_string()]),
+            "parse error: 1:1: a; 1:3: b",
+        ),
+    ];
+    for (i, (error, expected)) in tests.into_iter().enumerate() {
+        assert_eq!(error.to_string(), expected, "Test [{}] - wrong message", i);
+    }
+}
+
+fn integer(value: i64) -> Object {
+    Object::Integer(Integer { value })
+}
+
+#[test]
+fn test_register_fn() {
+    let mut interp = Interpreter::new();
+    interp.register_fn("answer", 0, |_| Ok(integer(42)));
+    interp.register_fn("double", 1, |args| match &args[0] {
+        Object::Integer(i) => Ok(integer(i.value * 2)),
+        arg => Err(format!("expected Integer, got {:?}", arg.object_type())),
+    });
+    interp.register_fn("count", Arity::AtLeast(1), |args| {
+        Ok(integer(args.len() as i64))
+    });
+    // Calls back into Crab with the function it is given.
+    interp.register_fn("twice", 2, |args| {
+        let once = apply_function(args[0].clone(), vec![args[1].clone()]);
+        Ok(apply_function(args[0].clone(), vec![once]))
+    });
+    let tests = vec![
+        ("answer()", Ok("42".to_string())),
+        ("double(answer())", Ok("84".to_string())),
+        ("count(1, 2, 3)", Ok("3".to_string())),
+        ("let d = double; d(5)", Ok("10".to_string())),
+        ("compose(double, double)(3)", Ok("12".to_string())),
+        ("fn(f) { f(7) }(double)", Ok("14".to_string())),
+        ("twice(fn(x) { x + 1 }, 1)", Ok("3".to_string())),
+        ("twice(double, 5)", Ok("20".to_string())),
+        ("double", Ok("native double".to_string())),
+        (
+            "answer(1)",
+            Err(CrabError::Runtime(
+                "Wrong number of arguments to `answer`: got 1, want 0".to_string(),
+            )),
+        ),
+        (
+            "count()",
+            Err(CrabError::Runtime(
+                "Wrong number of arguments to `count`: got 0, want at least 1".to_string(),
+            )),
+        ),
+        (
+            "double(true)",
+            Err(CrabError::Runtime(
+                "double: expected Integer, got Boolean".to_string(),
+            )),
+        ),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        assert_eval(&mut interp, i, input, expected);
+    }
+
+    let double = interp.get_global("double").unwrap();
+    assert_eq!(double.object_type(), ObjectType::Native);
+    assert_eq!(
+        apply_function(double, vec![integer(21)]),
+        integer(42),
+        "Native function can't be applied from Rust"
+    );
+}
+
+#[test]
+fn test_register_fn_captures_state() {
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicI64::new(0));
+    let counter = calls.clone();
+    let mut interp = Interpreter::new();
+    interp.register_fn("tick", 0, move |_| {
+        Ok(integer(counter.fetch_add(1, Ordering::SeqCst) + 1))
+    });
+    interp.eval_str("tick(); tick();").unwrap();
+    assert_eq!(interp.eval_str("tick()").unwrap(), integer(3));
+    assert_eq!(
+        calls.load(Ordering::SeqCst),
+        3,
+        "Closure state wasn't shared"
+    );
+}
+
+#[test]
+fn test_into_crab() {
+    let tests = vec![
+        (42.into_crab(), "42"),
+        ((-1).into_crab(), "-1"),
+        (true.into_crab(), "true"),
+        (().into_crab(), "null"),
+        (Some(7).into_crab(), "7"),
+        (None::<bool>.into_crab(), "null"),
+        (Some(Some(false)).into_crab(), "false"),
+        (integer(3).into_crab(), "3"),
+    ];
+    for (i, (value, expected)) in tests.into_iter().enumerate() {
+        assert_eq!(value.inspect(), expected, "Test [{}] - wrong object", i);
+    }
+}
+
+#[test]
+fn test_from_crab() {
+    let mut interp = Interpreter::new();
+    let mut eval = |input: &str| interp.eval_str(input).unwrap();
+
+    assert_eq!(i64::from_crab(eval("6 * 7")), Ok(42));
+    assert_eq!(bool::from_crab(eval("1 < 2")), Ok(true));
+    assert_eq!(<()>::from_crab(eval("if (false) { 1 }")), Ok(()));
+    assert_eq!(Option::<i64>::from_crab(eval("5")), Ok(Some(5)));
+    assert_eq!(Option::<i64>::from_crab(eval("if (false) { 1 }")), Ok(None));
+    assert_eq!(Object::from_crab(eval("1 < 2")), Ok(true.into_crab()));
+
+    let tests = vec![
+        (
+            i64::from_crab(eval("true")),
+            "expected Integer, got Boolean",
+        ),
+        (
+            i64::from_crab(eval("9223372036854775807 + 1")),
+            "integer 9223372036854775808 is out of range",
+        ),
+        (
+            i64::from_crab(eval("rational(1, 2)")),
+            "expected Integer, got Rational",
+        ),
+        (
+            i64::from_crab(eval("if (false) { 1 }")),
+            "expected Integer, got Null",
+        ),
+        (
+            Option::<bool>::from_crab(eval("1")).map(|_| 0),
+            "expected Boolean, got Integer",
+        ),
+        (
+            <()>::from_crab(eval("identity")).map(|_| 0),
+            "expected Null, got Function",
+        ),
+    ];
+    for (i, (actual, expected)) in tests.into_iter().enumerate() {
+        assert_eq!(
+            actual,
+            Err(expected.to_string()),
+            "Test [{}] - wrong error",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_conversions_in_host_code() {
+    let mut interp = Interpreter::new();
+    interp.set_global("limit", 10);
+    interp.set_global("strict", true);
+    interp.set_global("fallback", None::<i64>);
+    interp.register_fn("clamp", 1, |args| {
+        let n = i64::from_crab(args[0].clone())?;
+        Ok(n.clamp(0, 100).into_crab())
+    });
+    let tests = vec![
+        ("clamp(limit * 20)", Ok("100".to_string())),
+        ("if (strict) { clamp(-5) } else { 1 }", Ok("0".to_string())),
+        ("fallback", Ok("null".to_string())),
+        (
+            "clamp(strict)",
+            Err(CrabError::Runtime(
+                "clamp: expected Integer, got Boolean".to_string(),
+            )),
+        ),
+    ];
+    for (i, (input, expected)) in tests.into_iter().enumerate() {
+        assert_eval(&mut interp, i, input, expected);
+    }
+}
+
+#[test]
+fn test_set_output() {
+    let mut interp = Interpreter::new();
+    let printed = OutputBuffer::new();
+    interp.set_output(printed.clone());
+    interp
+        .eval_str("let log = fn(x) { puts(x); x }; log(1) + log(2)")
+        .unwrap();
+    assert_eq!(printed.take(), "1\n2\n", "Output wasn't captured");
+
+    // A Vec<u8> or io::sink() is an output too.
+    interp.set_output(Vec::new());
+    interp.eval_str("puts(3)").unwrap();
+    interp.set_output(std::io::sink());
+    interp.eval_str("puts(4)").unwrap();
+    assert_eq!(printed.take(), "", "Output went to a replaced sink");
+}
+
+#[test]
+fn test_limits() {
+    let nested = "fn(a) { fn(b) { fn(c) { a + b + c }(3) }(2) }(1)";
+    let self_apply = "let w = fn(f) { f(f) }; w(w)";
+    let square = "let sq = fn(x) { x * x };";
+    let tests = vec![
+        (Limits::default(), nested, Ok("6".to_string())),
+        (
+            Limits::default(),
+            self_apply,
+            Err(CrabError::Interrupted(Interrupt::DepthLimit)),
+        ),
+        (
+            Limits {
+                max_depth: Some(3),
+                ..Limits::default()
+            },
+            nested,
+            Ok("6".to_string()),
+        ),
+        (
+            Limits {
+                max_depth: Some(2),
+                ..Limits::default()
+            },
+            nested,
+            Err(CrabError::Interrupted(Interrupt::DepthLimit)),
+        ),
+        (
+            Limits {
+                max_steps: Some(20),
+                ..Limits::default()
+            },
+            nested,
+            Ok("6".to_string()),
+        ),
+        (
+            Limits {
+                max_steps: Some(10),
+                ..Limits::default()
+            },
+            nested,
+            Err(CrabError::Interrupted(Interrupt::StepLimit)),
+        ),
+        (
+            Limits {
+                max_steps: Some(100),
+                max_depth: None,
+                max_heap: None,
+            },
+            self_apply,
+            Err(CrabError::Interrupted(Interrupt::StepLimit)),
+        ),
+        // Without a depth limit, nesting still bounds the stack.
+        (
+            Limits {
+                max_depth: None,
+                ..Limits::default()
+            },
+            self_apply,
+            Err(CrabError::Interrupted(Interrupt::NestingLimit)),
+        ),
+        (
+            Limits {
+                max_heap: Some(10_000),
+                ..Limits::default()
+            },
+            "sq(sq(sq(sq(2))))",
+            Ok("65536".to_string()),
+        ),
+        (
+            Limits {
+                max_heap: Some(10_000),
+                ..Limits::default()
+            },
+            "sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(sq(2)))))))))))))))",
+            Err(CrabError::Interrupted(Interrupt::HeapLimit)),
+        ),
+        // Ordinary errors are still reported as such.
+        (
+            Limits {
+                max_steps: Some(10),
+                ..Limits::default()
+            },
+            "1 / 0",
+            Err(CrabError::Runtime("Division by zero".to_string())),
+        ),
+    ];
+    for (i, (limits, input, expected)) in tests.into_iter().enumerate() {
+        let mut interp = Interpreter::new();
+        interp.eval_str(square).unwrap();
+        interp.set_limits(limits);
+        assert_eval(&mut interp, i, input, expected);
+    }
+}
+
+#[test]
+fn test_limits_apply_per_evaluation() {
+    let mut interp = Interpreter::new();
+    interp.set_limits(Limits {
+        max_steps: Some(50),
+        max_depth: Some(10),
+        max_heap: Some(1_000_000),
+    });
+    interp.eval_str("let add = fn(a, b) { a + b };").unwrap();
+    for i in 0..100 {
+        let value = interp.eval_str("add(1, 2)").unwrap();
+        assert_eq!(value.inspect(), "3", "Evaluation {} ran out of budget", i);
+    }
+
+    // Running out doesn't break the interpreter for later evaluations.
+    let result = interp.eval_str("let w = fn(f) { f(f) }; w(w)");
+    assert_eq!(result, Err(CrabError::Interrupted(Interrupt::DepthLimit)));
+    assert_eq!(interp.eval_str("add(3, 4)").unwrap().inspect(), "7");
+
+    let tests = vec![
+        (Interrupt::StepLimit, "runtime error: Step limit exceeded"),
+        (
+            Interrupt::DepthLimit,
+            "runtime error: Call depth limit exceeded",
+        ),
+        (
+            Interrupt::NestingLimit,
+            "runtime error: Nesting limit exceeded",
+        ),
+        (Interrupt::HeapLimit, "runtime error: Memory limit exceeded"),
+    ];
+    for (i, (interrupt, expected)) in tests.into_iter().enumerate() {
+        let error = CrabError::Interrupted(interrupt);
+        assert_eq!(error.to_string(), expected, "Test [{}] - wrong message", i);
+    }
+}
+
+// An interpreter with `slow()`, which makes 2^12 calls to a native that
+// sleeps for a millisecond, so it runs for seconds unless stopped.
+fn slow_interpreter() -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.register_fn("tick", 0, |_| {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        Ok(().into_crab())
+    });
+    interp
+        .eval_str(
+            "let twice = fn(g) { fn() { g(); g() } };
+             let slow = twice(twice(twice(twice(twice(twice(
+                 twice(twice(twice(twice(twice(twice(tick))))))))))));",
+        )
+        .unwrap();
+    interp
+}
+
+#[test]
+fn test_cancel() {
+    use std::time::{Duration, Instant};
+
+    let mut interp = slow_interpreter();
+    let token = interp.cancel_token();
+    let canceller = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        token.cancel();
+    });
+    let start = Instant::now();
+    let result = interp.eval_str("slow()");
+    canceller.join().unwrap();
+    assert_eq!(result, Err(CrabError::Interrupted(Interrupt::Cancelled)));
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "Cancelling took {:?}",
+        start.elapsed()
+    );
+
+    // The token stays cancelled until it is replaced.
+    let result = interp.eval_str("1 + 1");
+    assert_eq!(result, Err(CrabError::Interrupted(Interrupt::Cancelled)));
+    interp.set_cancel_token(CancelToken::new());
+    assert_eq!(interp.eval_str("1 + 1").unwrap().inspect(), "2");
+    assert_eq!(
+        CrabError::Interrupted(Interrupt::Cancelled).to_string(),
+        "runtime error: Evaluation cancelled"
+    );
+}
+
+#[test]
+fn test_deadline() {
+    use std::time::{Duration, Instant};
+
+    let mut interp = slow_interpreter();
+    let start = Instant::now();
+    interp.set_deadline(Some(start + Duration::from_millis(20)));
+    let result = interp.eval_str("slow()");
+    assert_eq!(result, Err(CrabError::Interrupted(Interrupt::Timeout)));
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "Timing out took {:?}",
+        start.elapsed()
+    );
+
+    // A deadline that has passed stops everything until it is cleared.
+    let result = interp.eval_str("1 + 1");
+    assert_eq!(result, Err(CrabError::Interrupted(Interrupt::Timeout)));
+    interp.set_deadline(None);
+    assert_eq!(interp.eval_str("1 + 1").unwrap().inspect(), "2");
+    interp.set_deadline(Some(Instant::now() + Duration::from_secs(60)));
+    assert_eq!(interp.eval_str("twice(tick)()").unwrap().inspect(), "null");
+    assert_eq!(
+        CrabError::Interrupted(Interrupt::Timeout).to_string(),
+        "runtime error: Evaluation timed out"
+    );
+}
@@ -1,32 +1,51 @@
-use crate::lexer_utils::token::{Keywords, Token, TokenType};
+use crate::lexer_utils::token::{Comment, Keywords, Position, Token, TokenType};
+use std::collections::HashMap;
+use unicode_xid::UnicodeXID;
 
 #[derive(Debug)]
 pub struct Lexer {
     pub input: String,
+    chars: Vec<char>,
     pub keywords: Keywords,
     pub position: usize,      // current position
     pub read_position: usize, // next position
     pub ch: char,             // current char under analysis
+    pub line: usize,          // line of the current char
+    pub column: usize,        // column of the current char
+    pub comments: Vec<Comment>,
+    last_token_line: usize,
+    done: bool, // EOF already yielded by the iterator
 }
 
 impl Lexer {
     pub fn new(input: String) -> Lexer {
         let mut l = Lexer {
-            input: input,
+            chars: input.chars().collect(),
+            input,
             keywords: Keywords::new(),
             position: 0,
             read_position: 0,
             ch: '\0',
+            line: 1,
+            column: 0,
+            comments: Vec::new(),
+            last_token_line: 0,
+            done: false,
         };
         l.read_char();
         l
     }
 
     pub fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 0;
+        }
+        self.column += 1;
+        if self.read_position >= self.chars.len() {
             self.ch = '\0';
         } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap()
+            self.ch = self.chars[self.read_position]
         }
         self.position = self.read_position;
         self.read_position += 1;
@@ -34,7 +53,15 @@ impl Lexer {
 
     pub fn next_token(&mut self) -> Token {
         self.skip_space();
-        let mut tok: Token;
+        let position = Position::new(self.line, self.column);
+        self.last_token_line = self.line;
+        let mut tok = self.read_token();
+        tok.position = position;
+        tok
+    }
+
+    fn read_token(&mut self) -> Token {
+        let tok: Token;
         match self.ch {
             '(' => tok = Token::new_token(TokenType::LPAREN, self.ch.to_string()),
             ')' => tok = Token::new_token(TokenType::RPAREN, self.ch.to_string()),
@@ -66,18 +93,17 @@ impl Lexer {
             }
             '\0' => tok = Token::new_token(TokenType::EOF, self.ch.to_string()),
             _ => {
-                if self.is_letter() {
+                if self.is_identifier_start() {
                     let keyword = self.read_identifier();
                     let tok_type = self.keywords.check_ident(&keyword);
                     tok = Token::new_token(tok_type, keyword);
-                    return tok
-                } else if self.ch.is_alphanumeric() {
+                    return tok;
+                } else if self.ch.is_ascii_digit() {
                     let int = self.read_int();
                     tok = Token::new_token(TokenType::INT, int);
-                    return tok
+                    return tok;
                 } else {
-                    tok  =Token::new_token(TokenType::ILLEGAL, self.ch.to_string());
-                    return tok
+                    tok = Token::new_token(TokenType::ILLEGAL, self.ch.to_string());
                 }
             }
         };
@@ -87,35 +113,100 @@ impl Lexer {
 
     fn read_identifier(&mut self) -> String {
         let position = self.position;
-        while self.is_letter() {
+        while self.ch.is_xid_continue() {
             self.read_char();
         }
-        self.input[position..self.position].to_string()
+        self.chars[position..self.position].iter().collect()
     }
 
     fn read_int(&mut self) -> String {
         let position = self.position;
-        while self.ch.is_numeric() {
+        if self.ch == '0' && matches!(self.peek_char(), 'x' | 'o' | 'b') {
+            // Radix prefix: consume it and every alphanumeric that follows, so
+            // stray digits like `0b102` reach the parser as a single literal.
             self.read_char();
+            self.read_char();
+            while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+                self.read_char();
+            }
         }
-        self.input[position..self.position].to_string()
+        while self.ch.is_ascii_digit() || self.ch == '_' {
+            self.read_char();
+        }
+        self.chars[position..self.position].iter().collect()
     }
 
-    fn is_letter(&mut self) -> bool {
-        self.ch.is_alphabetic() || self.ch == '_'
+    fn is_identifier_start(&mut self) -> bool {
+        self.ch.is_xid_start() || self.ch == '_'
     }
 
     fn peek_char(&mut self) -> char {
-        if self.read_position >= self.input.len() {
+        if self.read_position >= self.chars.len() {
             '\0'
         } else {
-            self.input.chars().nth(self.read_position).unwrap()
+            self.chars[self.read_position]
         }
     }
 
     fn skip_space(&mut self) {
-        while self.ch == ' ' || self.ch == '\t' || self.ch == '\n' || self.ch == '\r' {
+        loop {
+            while self.ch == ' ' || self.ch == '\t' || self.ch == '\n' || self.ch == '\r' {
+                self.read_char();
+            }
+            if self.ch == '/' && self.peek_char() == '/' {
+                self.read_comment();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read_comment(&mut self) {
+        let position = Position::new(self.line, self.column);
+        let start = self.position;
+        while self.ch != '\n' && self.ch != '\0' {
             self.read_char();
         }
+        let text: String = self.chars[start..self.position].iter().collect();
+        self.comments.push(Comment {
+            text: text.trim_end().to_string(),
+            position,
+            own_line: self.last_token_line != position.line,
+        });
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Token;
+
+    /// Yields tokens up to and including `EOF`, then stops.
+    fn next(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
+        }
+        let tok = self.next_token();
+        if tok.token_type == TokenType::EOF {
+            self.done = true;
+        }
+        Some(tok)
+    }
+}
+
+// Maps the position of every `(` and `{` to the position of the token that
+// closes it. Unbalanced delimiters are left out.
+pub fn matching_delimiters(tokens: &[Token]) -> HashMap<Position, Position> {
+    let mut closers = HashMap::new();
+    let mut open = Vec::new();
+    for tok in tokens {
+        match tok.token_type {
+            TokenType::LPAREN | TokenType::LBRACE => open.push(tok),
+            TokenType::RPAREN | TokenType::RBRACE => {
+                if let Some(start) = open.pop() {
+                    closers.insert(start.position, tok.position);
+                }
+            }
+            _ => {}
+        }
     }
+    closers
 }
@@ -1,5 +1,5 @@
 use crate::lexer_utils::lexer::Lexer;
-use crate::lexer_utils::token::TokenType;
+use crate::lexer_utils::token::{Position, Token, TokenType};
 
 #[test]
 fn test_lexer() {
@@ -42,4 +42,108 @@ fn test_lexer() {
         let tok = l.next_token();
         assert_eq!(tok.literal, tt.0, "Test [{}] - Token Literal is wrong", i);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_lexer_iterator() {
+    let input = String::from("let x = 5;");
+    let tokens: Vec<Token> = Lexer::new(input).collect();
+    assert_eq!(tokens.len(), 6, "Token count is wrong");
+    assert_eq!(
+        tokens.last().unwrap().token_type,
+        TokenType::EOF,
+        "Last token is not EOF"
+    );
+
+    let idents = Lexer::new("let a = b + c;".to_string())
+        .filter(|t| t.token_type == TokenType::IDENT)
+        .count();
+    assert_eq!(idents, 3, "Identifier count is wrong");
+}
+
+#[test]
+fn test_token_positions() {
+    let input = String::from("let x = 5;\n  x + 10;");
+    let tests = vec![
+        ("let", Position::new(1, 1)),
+        ("x", Position::new(1, 5)),
+        ("=", Position::new(1, 7)),
+        ("5", Position::new(1, 9)),
+        (";", Position::new(1, 10)),
+        ("x", Position::new(2, 3)),
+        ("+", Position::new(2, 5)),
+        ("10", Position::new(2, 7)),
+        (";", Position::new(2, 9)),
+    ];
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert_eq!(tok.literal, tt.0, "Test [{}] - Token Literal is wrong", i);
+        assert_eq!(tok.position, tt.1, "Test [{}] - Token Position is wrong", i);
+    }
+}
+
+#[test]
+fn test_unicode_characters() {
+    let input = String::from("½ ٣ ² café _x1 x٣ 42");
+    let tests = [
+        ("½", TokenType::ILLEGAL),
+        ("٣", TokenType::ILLEGAL),
+        ("²", TokenType::ILLEGAL),
+        ("café", TokenType::IDENT),
+        ("_x1", TokenType::IDENT),
+        ("x٣", TokenType::IDENT),
+        ("42", TokenType::INT),
+        ("\0", TokenType::EOF),
+    ];
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert_eq!(tok.literal, tt.0, "Test [{}] - Token Literal is wrong", i);
+        assert_eq!(tok.token_type, tt.1, "Test [{}] - Token Type is wrong", i);
+    }
+}
+
+#[test]
+fn test_integer_literal_forms() {
+    let input = String::from("0xFF 0o755 0b1010 1_000_000 0b102 7");
+    let tests = [
+        ("0xFF", TokenType::INT),
+        ("0o755", TokenType::INT),
+        ("0b1010", TokenType::INT),
+        ("1_000_000", TokenType::INT),
+        ("0b102", TokenType::INT),
+        ("7", TokenType::INT),
+        ("\0", TokenType::EOF),
+    ];
+    let mut l = Lexer::new(input);
+    for (i, tt) in tests.iter().enumerate() {
+        let tok = l.next_token();
+        assert_eq!(tok.literal, tt.0, "Test [{}] - Token Literal is wrong", i);
+        assert_eq!(tok.token_type, tt.1, "Test [{}] - Token Type is wrong", i);
+    }
+}
+
+#[test]
+fn test_comments() {
+    let input = String::from("// leading\nlet x = 5; // trailing\n//last");
+    let mut l = Lexer::new(input);
+    let literals: Vec<String> = l.by_ref().map(|t| t.literal).collect();
+    assert_eq!(
+        literals,
+        ["let", "x", "=", "5", ";", "\0"],
+        "Tokens are wrong"
+    );
+
+    let expected = [
+        ("// leading", Position::new(1, 1), true),
+        ("// trailing", Position::new(2, 12), false),
+        ("//last", Position::new(3, 1), true),
+    ];
+    assert_eq!(l.comments.len(), expected.len(), "Comment count is wrong");
+    for (comment, tt) in l.comments.iter().zip(expected.iter()) {
+        assert_eq!(comment.text, tt.0, "Comment text is wrong");
+        assert_eq!(comment.position, tt.1, "Comment position is wrong");
+        assert_eq!(comment.own_line, tt.2, "Comment own_line is wrong");
+    }
+}
@@ -1,4 +1,6 @@
 use ::std::collections::HashMap;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq, Copy, Hash, Eq)]
 pub enum TokenType {
     ILLEGAL,
@@ -30,19 +32,55 @@ pub enum TokenType {
     RETURN,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+impl Position {
+    pub fn new(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub position: Position,
 }
 impl Token {
     pub fn new_token(token_type: TokenType, literal: String) -> Token {
         Token {
-            token_type: token_type,
-            literal: literal,
+            token_type,
+            literal,
+            position: Position::default(),
         }
     }
+
+    // Position just past the token. Tokens never span lines and their literal
+    // is the exact source text.
+    pub fn end(&self) -> Position {
+        Position::new(
+            self.position.line,
+            self.position.column + self.literal.chars().count(),
+        )
+    }
+}
+// A `//` comment. Comments are not tokens; the lexer collects them on the
+// side so tools like the formatter can put them back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub position: Position,
+    pub own_line: bool, // no token precedes it on its line
 }
+
 #[derive(Debug)]
 pub struct Keywords {
     pub map: HashMap<&'static str, TokenType>,
@@ -65,8 +103,14 @@ impl Keywords {
 
     pub fn check_ident(&self, keyword: &str) -> TokenType {
         match self.map.get(keyword) {
-            Some(k_word) => k_word.clone(),
-            None => TokenType::IDENT.clone(),
+            Some(k_word) => *k_word,
+            None => TokenType::IDENT,
         }
     }
 }
+
+impl Default for Keywords {
+    fn default() -> Keywords {
+        Keywords::new()
+    }
+}
@@ -0,0 +1,27 @@
+//! The Crab language as a library: the lexer, parser, evaluator, formatter
+//! and REPL behind the `interpreter` binary, and an [`Interpreter`] for
+//! embedding Crab in other programs.
+//!
+//! ```
+//! use interpreter::{FromCrab, Interpreter};
+//!
+//! let mut interp = Interpreter::new();
+//! interp.set_global("limit", 10);
+//! let value = interp.eval_str("max(limit, 3) * 2").unwrap();
+//! assert_eq!(i64::from_crab(value), Ok(20));
+//! ```
+
+pub mod evaluator_utils;
+pub mod formatter_utils;
+pub mod interpreter_utils;
+pub mod lexer_utils;
+pub mod parser_utils;
+pub mod repl_utils;
+
+// The embedding API. Hosts should only need these paths; the modules above
+// are laid out for the binary and may change.
+pub use evaluator_utils::evaluator::apply_function;
+pub use evaluator_utils::object::Arity;
+pub use evaluator_utils::runtime::{CancelToken, Interrupt, Limits, Output, OutputBuffer};
+pub use interpreter_utils::convert::{FromCrab, IntoCrab};
+pub use interpreter_utils::interpreter::{CrabError, Interpreter, Value};
@@ -1,10 +1,6 @@
-#![allow(unused)]
-
-mod lexer_utils;
-mod evaluator_utils;
-mod parser_utils;
-mod repl;
+mod cli;
 
 fn main() {
-    repl::start();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    cli::dispatch(&args);
 }
@@ -321,7 +321,7 @@ impl IfExpression {
             self.condition.string(),
             self.consequence.string()
         ));
-        let alternative = match &self.alternative {
+        match &self.alternative {
             Some(alt) => if_expr.push_str(&format!(" else {{ {} }}", alt.string())),
             None => if_expr.push_str(""),
         };
@@ -375,12 +375,12 @@ impl CallExpression {
     }
 }
 
+#[derive(Debug)]
 pub struct Program {
     pub statements: Vec<Node>,
 }
 impl Program {
     pub fn string(&self) -> String {
-        let mut program = String::new();
         let mut string = String::new();
         for stmt in &self.statements {
             match stmt {
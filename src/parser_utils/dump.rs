@@ -0,0 +1,396 @@
+// Stable, machine-readable renderings of the syntax tree: JSON with node
+// kinds, fields and source spans, and a compact S-expression form.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::lexer_utils::lexer::{matching_delimiters, Lexer};
+use crate::lexer_utils::token::{Position, Token};
+use crate::parser_utils::ast::{BlockStatement, Expression, Identifier, Node, Program, Statement};
+use crate::parser_utils::parser::{Groups, Parser};
+
+// Renders `program` as indented JSON. `tokens` is the token stream of the
+// source it was parsed from and is used to find where calls and blocks end;
+// without it those spans stop at their opening delimiter. `groups` comes from
+// the parser and widens spans over the parentheses around an expression.
+pub fn to_json(program: &Program, tokens: &[Token], groups: &Groups) -> String {
+    let spans = Spans {
+        closers: matching_delimiters(tokens),
+        groups,
+    };
+    let json = Json::Object(vec![
+        ("kind", Json::from("Program")),
+        (
+            "statements",
+            Json::Array(program.statements.iter().map(|n| spans.node(n)).collect()),
+        ),
+    ]);
+    let mut out = String::new();
+    json.write(&mut out, 0);
+    out
+}
+
+// Lexes and parses `input`, then renders it with `to_json`. Returns the
+// parser errors if the source doesn't parse.
+pub fn source_to_json(input: &str) -> Result<String, Vec<String>> {
+    let tokens: Vec<Token> = Lexer::new(input.to_string()).collect();
+    let mut p = Parser::new(Lexer::new(input.to_string()));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return Err(p.errors().clone());
+    }
+    Ok(to_json(&program, &tokens, p.groups()))
+}
+
+// Renders `program` as S-expressions, one top-level statement per line.
+pub fn to_sexp(program: &Program) -> String {
+    program
+        .statements
+        .iter()
+        .map(|n| format!("{}\n", node_sexp(n)))
+        .collect()
+}
+
+enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+impl Json {
+    fn is_scalar(&self) -> bool {
+        !matches!(self, Json::Array(_) | Json::Object(_))
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            Json::Number(n) => out.push_str(&n.to_string()),
+            Json::String(s) => write_string(out, s),
+            Json::Array(items) if items.iter().all(Json::is_scalar) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write(out, indent);
+                }
+                out.push(']');
+            }
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    item.write(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                newline(out, indent);
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+fn newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    out.push_str(&"  ".repeat(indent));
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Computes spans as (start, end) pairs, where `end` is just past the last
+// character of the node.
+struct Spans<'a> {
+    closers: HashMap<Position, Position>, // `(` or `{` position -> its closer
+    groups: &'a Groups,
+}
+impl Spans<'_> {
+    fn node(&self, node: &Node) -> Json {
+        match node {
+            Node::Statement(stmt) => self.statement(stmt),
+            Node::Expression(expr) => self.expression(expr),
+        }
+    }
+
+    fn statement(&self, stmt: &Statement) -> Json {
+        match stmt {
+            Statement::LetStatement(ls) => self.object(
+                "LetStatement",
+                (ls.token.position, self.span(&ls.value).1),
+                vec![
+                    ("name", self.identifier(&ls.name)),
+                    ("value", self.expression(&ls.value)),
+                ],
+            ),
+            Statement::ReturnStatement(rs) => self.object(
+                "ReturnStatement",
+                (rs.token.position, self.span(&rs.return_value).1),
+                vec![("value", self.expression(&rs.return_value))],
+            ),
+            Statement::ExpressionStatement(es) => self.object(
+                "ExpressionStatement",
+                (es.token.position, self.span(&es.expression).1),
+                vec![("expression", self.expression(&es.expression))],
+            ),
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> Json {
+        let span = self.span(expr);
+        match expr {
+            Expression::Identifier(id) => self.object(
+                "Identifier",
+                span,
+                vec![("value", Json::from(id.value.as_str()))],
+            ),
+            Expression::IntegerLiteral(il) => self.object(
+                "IntegerLiteral",
+                span,
+                vec![
+                    ("value", Json::Number(il.value)),
+                    ("literal", Json::from(il.token.literal.as_str())),
+                ],
+            ),
+            Expression::BooleanExpression(b) => self.object(
+                "BooleanExpression",
+                span,
+                vec![("value", Json::Bool(b.value))],
+            ),
+            Expression::PrefixExpression(pe) => self.object(
+                "PrefixExpression",
+                span,
+                vec![
+                    ("operator", Json::from(pe.operator.as_str())),
+                    ("right", self.expression(&pe.right)),
+                ],
+            ),
+            Expression::InfixExpression(ie) => self.object(
+                "InfixExpression",
+                span,
+                vec![
+                    ("operator", Json::from(ie.operator.as_str())),
+                    ("left", self.expression(&ie.left)),
+                    ("right", self.expression(&ie.right)),
+                ],
+            ),
+            Expression::IfExpression(ie) => self.object(
+                "IfExpression",
+                span,
+                vec![
+                    ("condition", self.expression(&ie.condition)),
+                    ("consequence", self.block(&ie.consequence)),
+                    (
+                        "alternative",
+                        match &ie.alternative {
+                            Some(alternative) => self.block(alternative),
+                            None => Json::Null,
+                        },
+                    ),
+                ],
+            ),
+            Expression::FunctionLiteral(fl) => self.object(
+                "FunctionLiteral",
+                span,
+                vec![
+                    (
+                        "parameters",
+                        Json::Array(fl.parameters.iter().map(|p| self.identifier(p)).collect()),
+                    ),
+                    ("body", self.block(&fl.body)),
+                ],
+            ),
+            Expression::CallExpression(ce) => self.object(
+                "CallExpression",
+                span,
+                vec![
+                    ("function", self.expression(&ce.function)),
+                    (
+                        "arguments",
+                        Json::Array(ce.arguments.iter().map(|a| self.expression(a)).collect()),
+                    ),
+                ],
+            ),
+            Expression::BlockStatement(bs) => self.block(bs),
+        }
+    }
+
+    fn identifier(&self, id: &Identifier) -> Json {
+        self.object(
+            "Identifier",
+            (id.token.position, id.token.end()),
+            vec![("value", Json::from(id.value.as_str()))],
+        )
+    }
+
+    fn block(&self, block: &BlockStatement) -> Json {
+        self.object(
+            "BlockStatement",
+            (block.token.position, self.closer_end(&block.token)),
+            vec![(
+                "statements",
+                Json::Array(block.statements.iter().map(|n| self.node(n)).collect()),
+            )],
+        )
+    }
+
+    fn object(
+        &self,
+        kind: &str,
+        span: (Position, Position),
+        fields: Vec<(&'static str, Json)>,
+    ) -> Json {
+        let mut object = vec![("kind", Json::from(kind)), ("span", span_json(span))];
+        object.extend(fields);
+        Json::Object(object)
+    }
+
+    fn span(&self, expr: &Expression) -> (Position, Position) {
+        let mut span = match expr {
+            Expression::Identifier(id) => (id.token.position, id.token.end()),
+            Expression::IntegerLiteral(il) => (il.token.position, il.token.end()),
+            Expression::BooleanExpression(b) => (b.token.position, b.token.end()),
+            Expression::PrefixExpression(pe) => (pe.token.position, self.span(&pe.right).1),
+            Expression::InfixExpression(ie) => (self.span(&ie.left).0, self.span(&ie.right).1),
+            Expression::IfExpression(ie) => match &ie.alternative {
+                Some(alternative) => (ie.token.position, self.closer_end(&alternative.token)),
+                None => (ie.token.position, self.closer_end(&ie.consequence.token)),
+            },
+            Expression::FunctionLiteral(fl) => (fl.token.position, self.closer_end(&fl.body.token)),
+            Expression::CallExpression(ce) => {
+                (self.span(&ce.function).0, self.closer_end(&ce.token))
+            }
+            Expression::BlockStatement(bs) => (bs.token.position, self.closer_end(&bs.token)),
+        };
+        // Parentheses around an expression are part of it, however many.
+        while let Some(outer) = self.groups.get(&span) {
+            span = *outer;
+        }
+        span
+    }
+
+    // End of the `)` or `}` matching `open`, or of `open` itself when the
+    // closer isn't known.
+    fn closer_end(&self, open: &Token) -> Position {
+        match self.closers.get(&open.position) {
+            Some(close) => Position::new(close.line, close.column + 1),
+            None => open.end(),
+        }
+    }
+}
+
+// `{"start": [line, column], "end": [line, column]}`
+fn span_json((start, end): (Position, Position)) -> Json {
+    let position = |p: Position| {
+        Json::Array(vec![
+            Json::Number(p.line as i64),
+            Json::Number(p.column as i64),
+        ])
+    };
+    Json::Object(vec![("start", position(start)), ("end", position(end))])
+}
+
+fn node_sexp(node: &Node) -> String {
+    match node {
+        Node::Statement(Statement::LetStatement(ls)) => {
+            format!("(let {} {})", ls.name.value, expression_sexp(&ls.value))
+        }
+        Node::Statement(Statement::ReturnStatement(rs)) => {
+            format!("(return {})", expression_sexp(&rs.return_value))
+        }
+        Node::Statement(Statement::ExpressionStatement(es)) => expression_sexp(&es.expression),
+        Node::Expression(expr) => expression_sexp(expr),
+    }
+}
+
+fn expression_sexp(expr: &Expression) -> String {
+    match expr {
+        Expression::Identifier(id) => id.value.clone(),
+        Expression::IntegerLiteral(il) => il.value.to_string(),
+        Expression::BooleanExpression(b) => b.value.to_string(),
+        Expression::PrefixExpression(pe) => {
+            format!("({} {})", pe.operator, expression_sexp(&pe.right))
+        }
+        Expression::InfixExpression(ie) => format!(
+            "({} {} {})",
+            ie.operator,
+            expression_sexp(&ie.left),
+            expression_sexp(&ie.right)
+        ),
+        Expression::IfExpression(ie) => {
+            let mut out = format!(
+                "(if {} {}",
+                expression_sexp(&ie.condition),
+                block_sexp(&ie.consequence)
+            );
+            if let Some(alternative) = &ie.alternative {
+                out.push(' ');
+                out.push_str(&block_sexp(alternative));
+            }
+            out.push(')');
+            out
+        }
+        Expression::FunctionLiteral(fl) => {
+            let parameters: Vec<&str> = fl.parameters.iter().map(|p| p.value.as_str()).collect();
+            format!("(fn ({}) {})", parameters.join(" "), block_sexp(&fl.body))
+        }
+        Expression::CallExpression(ce) => {
+            let mut out = format!("(call {}", expression_sexp(&ce.function));
+            for argument in &ce.arguments {
+                out.push(' ');
+                out.push_str(&expression_sexp(argument));
+            }
+            out.push(')');
+            out
+        }
+        Expression::BlockStatement(bs) => block_sexp(bs),
+    }
+}
+
+fn block_sexp(block: &BlockStatement) -> String {
+    let mut out = String::from("(block");
+    for node in &block.statements {
+        out.push(' ');
+        out.push_str(&node_sexp(node));
+    }
+    out.push(')');
+    out
+}
@@ -0,0 +1,234 @@
+// Rewriting traversal of the syntax tree. A `Fold` takes each node by value
+// and returns its replacement. Every `fold_*` hook defaults to the matching
+// free function in this module, which rebuilds the node from its folded
+// children, so an implementation only overrides the nodes it changes.
+//
+// To replace a node with one of a different kind, e.g. an infix expression
+// with the literal it evaluates to, override `fold_expression`.
+
+use crate::parser_utils::ast::{
+    BlockStatement, BooleanExpression, CallExpression, Expression, ExpressionStatement,
+    FunctionLiteral, Identifier, IfExpression, InfixExpression, IntegerLiteral, LetStatement, Node,
+    PrefixExpression, Program, ReturnStatement, Statement,
+};
+
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_node(&mut self, node: Node) -> Node {
+        fold_node(self, node)
+    }
+
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        fold_statement(self, stmt)
+    }
+
+    fn fold_let_statement(&mut self, stmt: LetStatement) -> LetStatement {
+        fold_let_statement(self, stmt)
+    }
+
+    fn fold_return_statement(&mut self, stmt: ReturnStatement) -> ReturnStatement {
+        fold_return_statement(self, stmt)
+    }
+
+    fn fold_expression_statement(&mut self, stmt: ExpressionStatement) -> ExpressionStatement {
+        fold_expression_statement(self, stmt)
+    }
+
+    fn fold_block_statement(&mut self, block: BlockStatement) -> BlockStatement {
+        fold_block_statement(self, block)
+    }
+
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        fold_expression(self, expr)
+    }
+
+    fn fold_identifier(&mut self, ident: Identifier) -> Identifier {
+        ident
+    }
+
+    fn fold_integer_literal(&mut self, int: IntegerLiteral) -> IntegerLiteral {
+        int
+    }
+
+    fn fold_boolean_expression(&mut self, boolean: BooleanExpression) -> BooleanExpression {
+        boolean
+    }
+
+    fn fold_prefix_expression(&mut self, expr: PrefixExpression) -> PrefixExpression {
+        fold_prefix_expression(self, expr)
+    }
+
+    fn fold_infix_expression(&mut self, expr: InfixExpression) -> InfixExpression {
+        fold_infix_expression(self, expr)
+    }
+
+    fn fold_if_expression(&mut self, expr: IfExpression) -> IfExpression {
+        fold_if_expression(self, expr)
+    }
+
+    fn fold_function_literal(&mut self, expr: FunctionLiteral) -> FunctionLiteral {
+        fold_function_literal(self, expr)
+    }
+
+    fn fold_call_expression(&mut self, expr: CallExpression) -> CallExpression {
+        fold_call_expression(self, expr)
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(f: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|n| f.fold_node(n))
+            .collect(),
+    }
+}
+
+pub fn fold_node<F: Fold + ?Sized>(f: &mut F, node: Node) -> Node {
+    match node {
+        Node::Statement(stmt) => Node::Statement(f.fold_statement(stmt)),
+        Node::Expression(expr) => Node::Expression(f.fold_expression(expr)),
+    }
+}
+
+pub fn fold_statement<F: Fold + ?Sized>(f: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::LetStatement(stmt) => Statement::LetStatement(f.fold_let_statement(stmt)),
+        Statement::ReturnStatement(stmt) => {
+            Statement::ReturnStatement(f.fold_return_statement(stmt))
+        }
+        Statement::ExpressionStatement(stmt) => {
+            Statement::ExpressionStatement(f.fold_expression_statement(stmt))
+        }
+    }
+}
+
+pub fn fold_let_statement<F: Fold + ?Sized>(f: &mut F, stmt: LetStatement) -> LetStatement {
+    LetStatement {
+        token: stmt.token,
+        name: f.fold_identifier(stmt.name),
+        value: f.fold_expression(stmt.value),
+    }
+}
+
+pub fn fold_return_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: ReturnStatement,
+) -> ReturnStatement {
+    ReturnStatement {
+        token: stmt.token,
+        return_value: f.fold_expression(stmt.return_value),
+    }
+}
+
+pub fn fold_expression_statement<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: ExpressionStatement,
+) -> ExpressionStatement {
+    ExpressionStatement {
+        token: stmt.token,
+        expression: Box::new(f.fold_expression(*stmt.expression)),
+    }
+}
+
+pub fn fold_block_statement<F: Fold + ?Sized>(f: &mut F, block: BlockStatement) -> BlockStatement {
+    BlockStatement {
+        token: block.token,
+        statements: block
+            .statements
+            .into_iter()
+            .map(|n| f.fold_node(n))
+            .collect(),
+    }
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(f: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::Identifier(expr) => Expression::Identifier(f.fold_identifier(expr)),
+        Expression::IntegerLiteral(expr) => {
+            Expression::IntegerLiteral(f.fold_integer_literal(expr))
+        }
+        Expression::BooleanExpression(expr) => {
+            Expression::BooleanExpression(f.fold_boolean_expression(expr))
+        }
+        Expression::PrefixExpression(expr) => {
+            Expression::PrefixExpression(f.fold_prefix_expression(expr))
+        }
+        Expression::InfixExpression(expr) => {
+            Expression::InfixExpression(f.fold_infix_expression(expr))
+        }
+        Expression::BlockStatement(expr) => {
+            Expression::BlockStatement(f.fold_block_statement(expr))
+        }
+        Expression::IfExpression(expr) => Expression::IfExpression(f.fold_if_expression(expr)),
+        Expression::FunctionLiteral(expr) => {
+            Expression::FunctionLiteral(f.fold_function_literal(expr))
+        }
+        Expression::CallExpression(expr) => {
+            Expression::CallExpression(f.fold_call_expression(expr))
+        }
+    }
+}
+
+pub fn fold_prefix_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: PrefixExpression,
+) -> PrefixExpression {
+    PrefixExpression {
+        token: expr.token,
+        operator: expr.operator,
+        right: Box::new(f.fold_expression(*expr.right)),
+    }
+}
+
+pub fn fold_infix_expression<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: InfixExpression,
+) -> InfixExpression {
+    InfixExpression {
+        token: expr.token,
+        left: Box::new(f.fold_expression(*expr.left)),
+        operator: expr.operator,
+        right: Box::new(f.fold_expression(*expr.right)),
+    }
+}
+
+pub fn fold_if_expression<F: Fold + ?Sized>(f: &mut F, expr: IfExpression) -> IfExpression {
+    IfExpression {
+        token: expr.token,
+        condition: Box::new(f.fold_expression(*expr.condition)),
+        consequence: f.fold_block_statement(expr.consequence),
+        alternative: expr.alternative.map(|a| f.fold_block_statement(a)),
+    }
+}
+
+pub fn fold_function_literal<F: Fold + ?Sized>(
+    f: &mut F,
+    expr: FunctionLiteral,
+) -> FunctionLiteral {
+    FunctionLiteral {
+        token: expr.token,
+        parameters: expr
+            .parameters
+            .into_iter()
+            .map(|p| f.fold_identifier(p))
+            .collect(),
+        body: f.fold_block_statement(expr.body),
+    }
+}
+
+pub fn fold_call_expression<F: Fold + ?Sized>(f: &mut F, expr: CallExpression) -> CallExpression {
+    CallExpression {
+        token: expr.token,
+        function: Box::new(f.fold_expression(*expr.function)),
+        arguments: expr
+            .arguments
+            .into_iter()
+            .map(|a| f.fold_expression(a))
+            .collect(),
+    }
+}
@@ -1,4 +1,13 @@
+#[allow(clippy::match_like_matches_macro)]
 pub mod ast;
+pub mod dump;
+pub mod fold;
+#[allow(
+    clippy::redundant_field_names,
+    clippy::question_mark,
+    clippy::unnecessary_unwrap
+)]
 pub mod parser;
+pub mod visit;
 #[cfg(test)]
-pub mod tests;
\ No newline at end of file
+pub mod tests;
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::num::IntErrorKind;
 
 use crate::lexer_utils::lexer::*;
 use crate::lexer_utils::token::*;
@@ -13,8 +14,18 @@ use crate::parser_utils::ast::{
 type PrefixParse = fn(&mut Parser) -> Result<Expression, String>;
 type InfixParse = fn(&mut Parser, Expression) -> Result<Expression, String>;
 
+// Spans `(start, end)` of parenthesized expressions including their
+// parentheses, keyed by the span of the expression inside them.
+pub type Groups = HashMap<(Position, Position), (Position, Position)>;
+
+// How deep expressions may nest. Everything that walks the syntax tree
+// recurses on it, so deeper input is rejected here rather than overflowing
+// the stack later. Each operator of a chain like `1 + 2 + 3` nests the
+// expression before it one level deeper.
+pub const MAX_NESTING: usize = 100;
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
-enum Precedence {
+pub enum Precedence {
     LOWEST = 0,
     EQUALS,
     LESSGREATER,
@@ -24,12 +35,29 @@ enum Precedence {
     CALL,
 }
 
+// Binding power of each infix operator token.
+pub fn precedence_table() -> HashMap<TokenType, Precedence> {
+    HashMap::from([
+        (TokenType::EQ, Precedence::EQUALS),
+        (TokenType::NOTEQ, Precedence::EQUALS),
+        (TokenType::LT, Precedence::LESSGREATER),
+        (TokenType::GT, Precedence::LESSGREATER),
+        (TokenType::PLUS, Precedence::SUM),
+        (TokenType::MINUS, Precedence::SUM),
+        (TokenType::ASTERISK, Precedence::PRODUCT),
+        (TokenType::SLASH, Precedence::PRODUCT),
+        (TokenType::LPAREN, Precedence::CALL),
+    ])
+}
+
 #[derive(Debug)]
 pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
     peek_token: Token,
     errors: Vec<String>,
+    groups: Groups,
+    depth: usize,
     prefix_parse: HashMap<TokenType, PrefixParse>,
     infix_parse: HashMap<TokenType, InfixParse>,
     precedence_table: HashMap<TokenType, Precedence>,
@@ -39,26 +67,16 @@ impl Parser {
         let cur_token = l.next_token();
         let peek_token = l.next_token();
 
-        let precedence_table = HashMap::from([
-            (TokenType::EQ, Precedence::EQUALS),
-            (TokenType::NOTEQ, Precedence::EQUALS),
-            (TokenType::LT, Precedence::LESSGREATER),
-            (TokenType::GT, Precedence::LESSGREATER),
-            (TokenType::PLUS, Precedence::SUM),
-            (TokenType::MINUS, Precedence::SUM),
-            (TokenType::ASTERISK, Precedence::PRODUCT),
-            (TokenType::SLASH, Precedence::PRODUCT),
-            (TokenType::LPAREN, Precedence::CALL),
-        ]);
-
         let mut p = Parser {
             lexer: l,
             cur_token: cur_token,
             peek_token: peek_token,
             errors: Vec::new(),
+            groups: HashMap::new(),
+            depth: 0,
             prefix_parse: HashMap::new(),
             infix_parse: HashMap::new(),
-            precedence_table: precedence_table,
+            precedence_table: precedence_table(),
         };
         p.register_parsers();
 
@@ -96,14 +114,37 @@ impl Parser {
         };
         while !self.cur_token_is(TokenType::EOF) {
             let stmt = self.parse_statement();
-            if stmt.is_ok() {
-                prg.statements.push(stmt.unwrap());
+            match stmt {
+                Ok(stmt) => prg.statements.push(stmt),
+                Err(_) => self.synchronize(),
             }
             self.next_token()
         }
         prg
     }
 
+    // Skips the rest of a statement that failed to parse, so one mistake
+    // doesn't cascade into errors for every token after it. Braces opened
+    // inside the statement are skipped with it; an unmatched `}` ends the
+    // enclosing block.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        while !self.cur_token_is(TokenType::EOF) {
+            if self.cur_token_is(TokenType::LBRACE) {
+                depth += 1;
+            } else if self.cur_token_is(TokenType::RBRACE) && depth > 0 {
+                depth -= 1;
+            }
+            if depth == 0
+                && (self.cur_token_is(TokenType::SEMICOLON)
+                    || self.peek_token_is(TokenType::RBRACE))
+            {
+                break;
+            }
+            self.next_token();
+        }
+    }
+
     fn parse_statement(&mut self) -> Result<Node, String> {
         match self.cur_token.token_type {
             TokenType::LET => self.parse_let_statement(),
@@ -141,8 +182,6 @@ impl Parser {
             value: value,
         }));
 
-        self.next_token();
-
         if self.peek_token_is(TokenType::SEMICOLON) {
             self.next_token();
         }
@@ -195,26 +234,51 @@ impl Parser {
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
+        let depth = self.depth;
+        let expression = self.parse_nested_expression(precedence);
+        self.depth = depth;
+        expression
+    }
+
+    fn parse_nested_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
+        self.nest()?;
+        if self.cur_token_is(TokenType::ILLEGAL) {
+            return Err(self.illegal_error());
+        }
         let prefix = self.prefix_parse.get(&self.cur_token.token_type);
 
         let mut left = match prefix {
-            Some(prefix_fn) => prefix_fn(self),
+            Some(prefix_fn) => prefix_fn(self)?,
             None => {
-                return Err(format!(
+                let e = format!(
                     "No prefix parse function for {:?}",
                     self.cur_token.token_type
-                ))
+                );
+                self.push_error(self.cur_token.position, e.clone());
+                return Err(e);
             }
         };
 
         while !self.peek_token_is(TokenType::SEMICOLON) && &precedence < self.peek_precedence() {
-            let infix_fn = self.infix_parse.get(&self.peek_token.token_type);
-            if infix_fn.is_none() {
-                return left;
-            }
-            left = infix_fn.unwrap()(self, left.unwrap());
+            let infix_fn = match self.infix_parse.get(&self.peek_token.token_type) {
+                Some(infix_fn) => *infix_fn,
+                None => return Ok(left),
+            };
+            left = infix_fn(self, left)?;
+            self.nest()?;
         }
-        left
+        Ok(left)
+    }
+
+    // Goes one level deeper into an expression, failing past MAX_NESTING.
+    fn nest(&mut self) -> Result<(), String> {
+        if self.depth == MAX_NESTING {
+            let e = format!("Expression nested more than {} levels deep", MAX_NESTING);
+            self.push_error(self.cur_token.position, e.clone());
+            return Err(e);
+        }
+        self.depth += 1;
+        Ok(())
     }
 
     fn parse_prefix_expression(&mut self) -> Result<Expression, String> {
@@ -267,10 +331,17 @@ impl Parser {
     }
 
     pub fn parse_grouped_expression(&mut self) -> Result<Expression, String> {
+        let open = self.cur_token.position;
         self.next_token();
-        let expr = self.parse_expression(Precedence::LOWEST);
+        let start = self.cur_token.position;
+        let expr = self.parse_expression(Precedence::LOWEST)?;
+        let end = self.cur_token.end();
         match self.expect_peek(TokenType::RPAREN) {
-            Ok(_) => expr,
+            Ok(_) => {
+                self.groups
+                    .insert((start, end), (open, self.cur_token.end()));
+                Ok(expr)
+            }
             Err(_) => Err("Expected closing parenthesis".to_string()),
         }
     }
@@ -283,7 +354,7 @@ impl Parser {
         }
 
         self.next_token();
-        let condition = self.parse_expression(Precedence::LOWEST);
+        let condition = self.parse_expression(Precedence::LOWEST)?;
 
         if self.expect_peek(TokenType::RPAREN).is_err() {
             return Err("Expected closing parenthesis".to_string());
@@ -304,7 +375,7 @@ impl Parser {
 
         Ok(Expression::IfExpression(IfExpression {
             token: token,
-            condition: Box::new(condition.unwrap()),
+            condition: Box::new(condition),
             consequence: consequence,
             alternative: alternative,
         }))
@@ -339,8 +410,9 @@ impl Parser {
         let mut statements = Vec::new();
         while !self.cur_token_is(TokenType::RBRACE) && !self.cur_token_is(TokenType::EOF) {
             let stmt = self.parse_statement();
-            if stmt.is_ok() {
-                statements.push(stmt.unwrap());
+            match stmt {
+                Ok(stmt) => statements.push(stmt),
+                Err(_) => self.synchronize(),
             }
             self.next_token();
         }
@@ -358,7 +430,9 @@ impl Parser {
             return Ok(identifiers);
         }
 
-        self.next_token();
+        if self.expect_peek(TokenType::IDENT).is_err() {
+            return Err("Expected a parameter name".to_string());
+        }
 
         identifiers.push(Identifier {
             token: self.cur_token.clone(),
@@ -367,7 +441,9 @@ impl Parser {
 
         while self.peek_token_is(TokenType::COMMA) {
             self.next_token();
-            self.next_token();
+            if self.expect_peek(TokenType::IDENT).is_err() {
+                return Err("Expected a parameter name".to_string());
+            }
             identifiers.push(Identifier {
                 token: self.cur_token.clone(),
                 value: self.cur_token.literal.clone(),
@@ -404,12 +480,12 @@ impl Parser {
 
         self.next_token();
 
-        args.push(self.parse_expression(Precedence::LOWEST).unwrap());
+        args.push(self.parse_expression(Precedence::LOWEST)?);
 
         while self.peek_token_is(TokenType::COMMA) {
             self.next_token();
             self.next_token();
-            args.push(self.parse_expression(Precedence::LOWEST).unwrap());
+            args.push(self.parse_expression(Precedence::LOWEST)?);
         }
         if self.expect_peek(TokenType::RPAREN).is_err() {
             return Err("Expected ')'".to_string());
@@ -425,15 +501,35 @@ impl Parser {
     }
 
     pub fn parse_integer_literal(&mut self) -> Result<Expression, String> {
-        let converted = self.cur_token.literal.parse::<i64>();
+        let literal = &self.cur_token.literal;
+        let (digits, radix) = match literal.get(..2) {
+            Some("0x") => (&literal[2..], 16),
+            Some("0o") => (&literal[2..], 8),
+            Some("0b") => (&literal[2..], 2),
+            _ => (literal.as_str(), 10),
+        };
+
+        let converted = if digits.replace('_', "").is_empty() || digits.ends_with('_') {
+            Err(format!("Invalid integer literal {}", literal))
+        } else {
+            i64::from_str_radix(&digits.replace('_', ""), radix).map_err(|e| match e.kind() {
+                IntErrorKind::PosOverflow => {
+                    format!(
+                        "Integer literal {} is too large for a 64-bit integer",
+                        literal
+                    )
+                }
+                _ => format!("Invalid integer literal {}", literal),
+            })
+        };
+
         match converted {
             Ok(n) => Ok(Expression::IntegerLiteral(IntegerLiteral {
                 token: self.cur_token.clone(),
                 value: n,
             })),
-            Err(_) => {
-                let e = format!("Could not parse {} as integer", self.cur_token.literal);
-                self.errors.push(e.clone());
+            Err(e) => {
+                self.push_error(self.cur_token.position, e.clone());
                 Err(e)
             }
         }
@@ -491,9 +587,26 @@ impl Parser {
             "Expected next token to be {:?}, got {:?} instead",
             t, self.peek_token.token_type
         );
-        self.errors.push(e);
+        self.push_error(self.peek_token.position, e);
+    }
+
+    fn illegal_error(&mut self) -> String {
+        let ch = self.cur_token.literal.chars().next().unwrap_or('\0');
+        let e = format!("Illegal character {:?} (U+{:04X})", ch, ch as u32);
+        self.push_error(self.cur_token.position, e.clone());
+        e
     }
+
+    // Errors are recorded as `line:column: message`.
+    fn push_error(&mut self, position: Position, message: String) {
+        self.errors.push(format!("{}: {}", position, message));
+    }
+
     pub fn errors(&mut self) -> &Vec<String> {
         &self.errors
     }
+
+    pub fn groups(&self) -> &Groups {
+        &self.groups
+    }
 }
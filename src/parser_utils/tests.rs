@@ -1,7 +1,10 @@
 use crate::lexer_utils::{lexer::Lexer, token::TokenType};
 use crate::parser_utils::{
-    ast::{BooleanExpression, Expression, Identifier, IntegerLiteral, Program},
+    ast::{BooleanExpression, Expression, FunctionLiteral, Identifier, IntegerLiteral, Program},
+    dump::{source_to_json, to_sexp},
+    fold::{self, Fold},
     parser::Parser,
+    visit::{self, Visitor},
 };
 
 fn init_program(input: String) -> Program {
@@ -11,6 +14,7 @@ fn init_program(input: String) -> Program {
 }
 
 #[test]
+#[allow(clippy::get_first, clippy::useless_vec)]
 fn test_let_statements() {
     struct LetTests {
         input: String,
@@ -65,6 +69,36 @@ fn test_let_statements() {
 }
 
 #[test]
+fn test_let_statements_without_semicolon() {
+    let tests = [
+        ("let x = 5\nx", vec!["x = 5;", "x"]),
+        ("let a = 1\nlet b = 2", vec!["a = 1;", "b = 2;"]),
+        ("let a = 1;\nlet b = 2;", vec!["a = 1;", "b = 2;"]),
+        ("fn() { let x = 1 }", vec!["fn (  ) { x = 1; }"]),
+    ];
+
+    for (i, (input, expected)) in tests.iter().enumerate() {
+        let l = Lexer::new(input.to_string());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        assert!(
+            p.errors().is_empty(),
+            "Test [{}] - unexpected errors {:?}",
+            i,
+            p.errors()
+        );
+
+        let actual: Vec<String> = program.statements.iter().map(|s| s.string()).collect();
+        assert_eq!(
+            &actual, expected,
+            "Test [{}] - wrong statements for {:?}",
+            i, input
+        );
+    }
+}
+
+#[test]
+#[allow(clippy::get_first, clippy::useless_vec)]
 fn test_return_statements() {
     struct ReturnTests {
         input: String,
@@ -110,6 +144,7 @@ fn test_return_statements() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_identifier_expression() {
     let input = String::from("foobar;");
     let program = init_program(input);
@@ -141,6 +176,7 @@ fn test_identifier_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_integer_literal_expression() {
     let input = String::from("5;");
     let program = init_program(input);
@@ -172,6 +208,7 @@ fn test_integer_literal_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_boolean_expresion() {
     struct BooleanTest {
         input: String,
@@ -224,6 +261,7 @@ fn test_boolean_expresion() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_prefix_expression() {
     struct PrefixTest {
         input: String,
@@ -319,6 +357,7 @@ fn test_prefix_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_infix_expression() {
     struct InfixTest {
         input: String,
@@ -556,6 +595,7 @@ fn test_operator_precedence() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison, clippy::get_first)]
 fn test_if_expression() {
     let input = "if (x < y) { x }".to_string();
     let p = init_program(input);
@@ -573,6 +613,7 @@ fn test_if_expression() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison, clippy::get_first)]
 fn test_if_else_expression() {
     let input = "if (x < y) { x } else { y }".to_string();
     let p = init_program(input);
@@ -598,6 +639,7 @@ fn test_if_else_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_function_literal_expression() {
     let input = "fn(x, y) { x + y; }".to_string();
     let p = init_program(input);
@@ -628,6 +670,7 @@ fn test_function_literal_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_function_literal_parameters() {
     struct ParameterTest {
         input: String,
@@ -672,6 +715,7 @@ fn test_function_literal_parameters() {
 }
 
 #[test]
+#[allow(clippy::get_first)]
 fn test_call_expression() {
     let input = "add(1, 2 * 3, 4 + 5);".to_string();
     let p = init_program(input);
@@ -695,6 +739,7 @@ fn test_call_expression() {
 }
 
 #[test]
+#[allow(clippy::get_first, clippy::needless_borrow, clippy::useless_vec)]
 fn test_call_expression_args() {
     struct CallExpressionTest {
         input: String,
@@ -754,6 +799,435 @@ fn test_call_expression_args() {
     }
 }
 
+#[test]
+fn test_extended_integer_literals() {
+    let tests = [
+        ("0xFF;", 255),
+        ("0xff;", 255),
+        ("0o755;", 493),
+        ("0b1010;", 10),
+        ("1_000_000;", 1_000_000),
+        ("0xFFFF_FFFF;", 4_294_967_295),
+        ("0b_1111_0000;", 240),
+    ];
+
+    for tt in tests {
+        let program = init_program(tt.0.to_string());
+        let stmts = program.statements;
+        assert_eq!(stmts.len(), 1, "Test [{}] Statement length is wrong", tt.0);
+
+        let expr = &stmts.first().unwrap().get_statement_expr().expression;
+        assert_eq!(
+            expr.get_integer_literal().value,
+            tt.1,
+            "Test [{}] Integer Literal Value is wrong",
+            tt.0
+        );
+    }
+}
+
+#[test]
+fn test_integer_literal_errors() {
+    let tests = [
+        (
+            "9223372036854775808;",
+            "1:1: Integer literal 9223372036854775808 is too large for a 64-bit integer",
+        ),
+        (
+            "0x1_0000_0000_0000_0000;",
+            "1:1: Integer literal 0x1_0000_0000_0000_0000 is too large for a 64-bit integer",
+        ),
+        ("0b102;", "1:1: Invalid integer literal 0b102"),
+        ("0x;", "1:1: Invalid integer literal 0x"),
+        ("1_000_;", "1:1: Invalid integer literal 1_000_"),
+    ];
+
+    for tt in tests {
+        let l = Lexer::new(tt.0.to_string());
+        let mut p = Parser::new(l);
+        p.parse_program();
+
+        let errors = p.errors();
+        assert_eq!(
+            errors.len(),
+            1,
+            "Test [{}] Parser error count is wrong",
+            tt.0
+        );
+        assert_eq!(errors[0], tt.1, "Test [{}] Parser error is wrong", tt.0);
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let input =
+        "let = 5;\nlet y 10;\nlet z = ;\nadd(1, );\nfn(1) { x };\nfn(a, true) { a };\nlet ok = 1;";
+    let l = Lexer::new(input.to_string());
+    let mut p = Parser::new(l);
+    let program = p.parse_program();
+
+    let expected = [
+        "1:5: Expected next token to be IDENT, got ASSIGN instead",
+        "2:7: Expected next token to be ASSIGN, got INT instead",
+        "3:9: No prefix parse function for SEMICOLON",
+        "4:8: No prefix parse function for RPAREN",
+        "5:4: Expected next token to be IDENT, got INT instead",
+        "6:7: Expected next token to be IDENT, got TRUE instead",
+    ];
+    assert_eq!(p.errors(), &expected, "Parser errors are wrong");
+    assert_eq!(program.statements.len(), 1, "Statement length is wrong");
+}
+
+#[test]
+fn test_nesting_limit() {
+    let error = "Expression nested more than 100 levels deep";
+    let tests = [
+        (format!("{}1", "-".repeat(99)), None),
+        (format!("{}1", "-".repeat(100)), Some("1:101")),
+        (format!("{}1", "-".repeat(200_000)), Some("1:101")),
+        (format!("{}1{}", "(".repeat(99), ")".repeat(99)), None),
+        (
+            format!("{}1{}", "(".repeat(20_000), ")".repeat(20_000)),
+            Some("1:101"),
+        ),
+        (format!("{}1", "1 + ".repeat(99)), None),
+        (format!("{}1", "1 + ".repeat(100_000)), Some("1:401")),
+        (
+            format!("{}1{}", "f(".repeat(100), ")".repeat(100)),
+            Some("1:201"),
+        ),
+        (
+            format!("{}1{}", "if (x) { ".repeat(100), " }".repeat(100)),
+            Some("1:896"),
+        ),
+    ];
+
+    for (i, (input, position)) in tests.iter().enumerate() {
+        let mut p = Parser::new(Lexer::new(input.clone()));
+        p.parse_program();
+        let expected: Vec<String> = position
+            .iter()
+            .map(|position| format!("{}: {}", position, error))
+            .collect();
+        assert_eq!(p.errors(), &expected, "Test [{}] - wrong errors", i);
+    }
+}
+
+#[test]
+fn test_illegal_character_errors() {
+    let l = Lexer::new("let x = ½;".to_string());
+    let mut p = Parser::new(l);
+    p.parse_program();
+
+    let errors = p.errors();
+    assert_eq!(errors.len(), 1, "Parser error count is wrong");
+    assert_eq!(
+        errors[0], "1:9: Illegal character '½' (U+00BD)",
+        "Parser error is wrong"
+    );
+}
+
+#[test]
+fn test_sexp_dump() {
+    let tests = [
+        ("let x = 5;", "(let x 5)\n"),
+        ("return -a * (b + c);", "(return (* (- a) (+ b c)))\n"),
+        ("!true == false", "(== (! true) false)\n"),
+        ("0xFF; 1_000", "255\n1000\n"),
+        (
+            "if (x < y) { x } else { y }",
+            "(if (< x y) (block x) (block y))\n",
+        ),
+        ("if (x) {}", "(if x (block))\n"),
+        (
+            "fn(a, b) { return a; b }",
+            "(fn (a b) (block (return a) b))\n",
+        ),
+        ("fn() { 1 }()", "(call (fn () (block 1)))\n"),
+        ("add(1, f(2))", "(call add 1 (call f 2))\n"),
+    ];
+
+    for (i, (input, expected)) in tests.iter().enumerate() {
+        let program = init_program(input.to_string());
+        assert_eq!(
+            to_sexp(&program),
+            *expected,
+            "Test [{}] - S-expression is wrong",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_json_dump() {
+    let expected = r#"{
+  "kind": "Program",
+  "statements": [
+    {
+      "kind": "LetStatement",
+      "span": {
+        "start": [1, 1],
+        "end": [1, 17]
+      },
+      "name": {
+        "kind": "Identifier",
+        "span": {
+          "start": [1, 5],
+          "end": [1, 6]
+        },
+        "value": "x"
+      },
+      "value": {
+        "kind": "CallExpression",
+        "span": {
+          "start": [1, 9],
+          "end": [1, 17]
+        },
+        "function": {
+          "kind": "Identifier",
+          "span": {
+            "start": [1, 9],
+            "end": [1, 10]
+          },
+          "value": "f"
+        },
+        "arguments": [
+          {
+            "kind": "PrefixExpression",
+            "span": {
+              "start": [1, 11],
+              "end": [1, 16]
+            },
+            "operator": "-",
+            "right": {
+              "kind": "IntegerLiteral",
+              "span": {
+                "start": [1, 12],
+                "end": [1, 16]
+              },
+              "value": 16,
+              "literal": "0x10"
+            }
+          }
+        ]
+      }
+    },
+    {
+      "kind": "ExpressionStatement",
+      "span": {
+        "start": [2, 1],
+        "end": [3, 2]
+      },
+      "expression": {
+        "kind": "IfExpression",
+        "span": {
+          "start": [2, 1],
+          "end": [3, 2]
+        },
+        "condition": {
+          "kind": "BooleanExpression",
+          "span": {
+            "start": [2, 5],
+            "end": [2, 9]
+          },
+          "value": true
+        },
+        "consequence": {
+          "kind": "BlockStatement",
+          "span": {
+            "start": [2, 11],
+            "end": [3, 2]
+          },
+          "statements": []
+        },
+        "alternative": null
+      }
+    }
+  ]
+}"#;
+
+    assert_eq!(
+        source_to_json("let x = f(-0x10);\nif (true) {\n}").unwrap(),
+        expected,
+        "JSON dump is wrong"
+    );
+    assert_eq!(
+        source_to_json("let = 5;").unwrap_err(),
+        vec!["1:5: Expected next token to be IDENT, got ASSIGN instead".to_string()],
+        "JSON dump should report parse errors"
+    );
+}
+
+#[test]
+fn test_json_dump_spans_parentheses() {
+    let tests = [
+        (
+            "(1 + 2) * 3",
+            vec![
+                "ExpressionStatement 1:1-1:12",
+                "InfixExpression 1:1-1:12",
+                "InfixExpression 1:1-1:8",
+                "IntegerLiteral 1:2-1:3",
+                "IntegerLiteral 1:6-1:7",
+                "IntegerLiteral 1:11-1:12",
+            ],
+        ),
+        (
+            "-(a + b)",
+            vec![
+                "ExpressionStatement 1:1-1:9",
+                "PrefixExpression 1:1-1:9",
+                "InfixExpression 1:2-1:9",
+                "Identifier 1:3-1:4",
+                "Identifier 1:7-1:8",
+            ],
+        ),
+        (
+            "f(( x ))",
+            vec![
+                "ExpressionStatement 1:1-1:9",
+                "CallExpression 1:1-1:9",
+                "Identifier 1:1-1:2",
+                "Identifier 1:3-1:8",
+            ],
+        ),
+    ];
+
+    for (i, (input, expected)) in tests.iter().enumerate() {
+        let json = source_to_json(input).unwrap();
+
+        // Pairs each node kind with the span that follows it.
+        let mut spans = Vec::new();
+        let mut kind = "";
+        let mut start = String::new();
+        for line in json.lines().map(str::trim) {
+            let value = |key: &str| line.strip_prefix(key).map(|v| v.trim_end_matches(','));
+            if let Some(k) = value("\"kind\": ") {
+                kind = k.trim_matches('"');
+            } else if let Some(s) = value("\"start\": ") {
+                start = s.trim_matches(&['[', ']'][..]).replace(", ", ":");
+            } else if let Some(e) = value("\"end\": ") {
+                let end = e.trim_matches(&['[', ']'][..]).replace(", ", ":");
+                spans.push(format!("{} {}-{}", kind, start, end));
+            }
+        }
+        assert_eq!(&spans, expected, "Test [{}] - wrong spans for {}", i, input);
+    }
+}
+
+#[test]
+fn test_visitor() {
+    // Records identifiers in visiting order, skipping function bodies when
+    // `skip_functions` is set.
+    struct Names {
+        names: Vec<String>,
+        skip_functions: bool,
+    }
+    impl Visitor for Names {
+        fn visit_identifier(&mut self, ident: &Identifier) {
+            self.names.push(ident.value.clone());
+        }
+
+        fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+            if !self.skip_functions {
+                visit::walk_function_literal(self, expr);
+            }
+        }
+    }
+
+    let tests = [
+        ("let x = a + -b;", false, vec!["x", "a", "b"]),
+        ("return f(x, g(y));", false, vec!["f", "x", "g", "y"]),
+        ("if (a) { b } else { c }", false, vec!["a", "b", "c"]),
+        (
+            "let f = fn(x) { x + y }; f(z)",
+            false,
+            vec!["f", "x", "x", "y", "f", "z"],
+        ),
+        ("let f = fn(x) { x + y }; f(z)", true, vec!["f", "f", "z"]),
+    ];
+
+    for (i, (input, skip_functions, expected)) in tests.iter().enumerate() {
+        let program = init_program(input.to_string());
+        let mut names = Names {
+            names: Vec::new(),
+            skip_functions: *skip_functions,
+        };
+        names.visit_program(&program);
+        assert_eq!(
+            names.names, *expected,
+            "Test [{}] - Visited identifiers are wrong",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_fold() {
+    // Replaces sums and products of integer literals with their value.
+    struct ConstantFolder;
+    impl Fold for ConstantFolder {
+        fn fold_expression(&mut self, expr: Expression) -> Expression {
+            match fold::fold_expression(self, expr) {
+                Expression::InfixExpression(ie) => match (ie.left.as_ref(), ie.right.as_ref()) {
+                    (Expression::IntegerLiteral(l), Expression::IntegerLiteral(r)) => {
+                        let value = match ie.operator.as_str() {
+                            "+" => l.value.checked_add(r.value),
+                            "*" => l.value.checked_mul(r.value),
+                            _ => None,
+                        };
+                        match value {
+                            Some(value) => Expression::IntegerLiteral(IntegerLiteral {
+                                token: l.token.clone(),
+                                value,
+                            }),
+                            None => Expression::InfixExpression(ie),
+                        }
+                    }
+                    _ => Expression::InfixExpression(ie),
+                },
+                expr => expr,
+            }
+        }
+    }
+
+    // Renames every identifier, binding sites included.
+    struct Renamer;
+    impl Fold for Renamer {
+        fn fold_identifier(&mut self, mut ident: Identifier) -> Identifier {
+            ident.value = format!("{}_1", ident.value);
+            ident
+        }
+    }
+
+    let tests = [
+        ("1 + 2 * 3", "7\n"),
+        ("let x = (1 + 2) * y;", "(let x (* 3 y))\n"),
+        ("if (a < 1 + 1) { 2 * 2 }", "(if (< a 2) (block 4))\n"),
+        (
+            "f(1 + 1, fn() { return 3 * 3; })",
+            "(call f 2 (fn () (block (return 9))))\n",
+        ),
+        ("1 - 2", "(- 1 2)\n"),
+    ];
+    for (i, (input, expected)) in tests.iter().enumerate() {
+        let program = ConstantFolder.fold_program(init_program(input.to_string()));
+        assert_eq!(
+            to_sexp(&program),
+            *expected,
+            "Test [{}] - Folded program is wrong",
+            i
+        );
+    }
+
+    let program = Renamer.fold_program(init_program("let f = fn(x) { x + y }; f(z)".to_string()));
+    assert_eq!(
+        to_sexp(&program),
+        "(let f_1 (fn (x_1) (block (+ x_1 y_1))))\n(call f_1 z_1)\n",
+        "Renamed program is wrong"
+    );
+}
+
 fn test_literal_expression(expr: &Expression, expected: &str) {
     match expr {
         Expression::Identifier(_) => test_identifier(expr.get_identifer(), expected),
@@ -0,0 +1,158 @@
+// Read-only traversal of the syntax tree. Every `visit_*` hook defaults to the
+// matching `walk_*` function, which visits the node's children in source
+// order. Override a hook to act on that kind of node, and call the `walk_*`
+// function from it to keep descending.
+//
+// Identifiers are visited wherever they appear, including the name of a `let`
+// and the parameters of a function.
+
+use crate::parser_utils::ast::{
+    BlockStatement, BooleanExpression, CallExpression, Expression, ExpressionStatement,
+    FunctionLiteral, Identifier, IfExpression, InfixExpression, IntegerLiteral, LetStatement, Node,
+    PrefixExpression, Program, ReturnStatement, Statement,
+};
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_let_statement(&mut self, stmt: &LetStatement) {
+        walk_let_statement(self, stmt);
+    }
+
+    fn visit_return_statement(&mut self, stmt: &ReturnStatement) {
+        walk_return_statement(self, stmt);
+    }
+
+    fn visit_expression_statement(&mut self, stmt: &ExpressionStatement) {
+        walk_expression_statement(self, stmt);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_identifier(&mut self, _ident: &Identifier) {}
+
+    fn visit_integer_literal(&mut self, _int: &IntegerLiteral) {}
+
+    fn visit_boolean_expression(&mut self, _boolean: &BooleanExpression) {}
+
+    fn visit_prefix_expression(&mut self, expr: &PrefixExpression) {
+        walk_prefix_expression(self, expr);
+    }
+
+    fn visit_infix_expression(&mut self, expr: &InfixExpression) {
+        walk_infix_expression(self, expr);
+    }
+
+    fn visit_if_expression(&mut self, expr: &IfExpression) {
+        walk_if_expression(self, expr);
+    }
+
+    fn visit_function_literal(&mut self, expr: &FunctionLiteral) {
+        walk_function_literal(self, expr);
+    }
+
+    fn visit_call_expression(&mut self, expr: &CallExpression) {
+        walk_call_expression(self, expr);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, program: &Program) {
+    for node in &program.statements {
+        v.visit_node(node);
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(v: &mut V, node: &Node) {
+    match node {
+        Node::Statement(stmt) => v.visit_statement(stmt),
+        Node::Expression(expr) => v.visit_expression(expr),
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::LetStatement(stmt) => v.visit_let_statement(stmt),
+        Statement::ReturnStatement(stmt) => v.visit_return_statement(stmt),
+        Statement::ExpressionStatement(stmt) => v.visit_expression_statement(stmt),
+    }
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &LetStatement) {
+    v.visit_identifier(&stmt.name);
+    v.visit_expression(&stmt.value);
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ReturnStatement) {
+    v.visit_expression(&stmt.return_value);
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(v: &mut V, stmt: &ExpressionStatement) {
+    v.visit_expression(&stmt.expression);
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(v: &mut V, block: &BlockStatement) {
+    for node in &block.statements {
+        v.visit_node(node);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(v: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Identifier(expr) => v.visit_identifier(expr),
+        Expression::IntegerLiteral(expr) => v.visit_integer_literal(expr),
+        Expression::BooleanExpression(expr) => v.visit_boolean_expression(expr),
+        Expression::PrefixExpression(expr) => v.visit_prefix_expression(expr),
+        Expression::InfixExpression(expr) => v.visit_infix_expression(expr),
+        Expression::BlockStatement(expr) => v.visit_block_statement(expr),
+        Expression::IfExpression(expr) => v.visit_if_expression(expr),
+        Expression::FunctionLiteral(expr) => v.visit_function_literal(expr),
+        Expression::CallExpression(expr) => v.visit_call_expression(expr),
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(v: &mut V, expr: &PrefixExpression) {
+    v.visit_expression(&expr.right);
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(v: &mut V, expr: &InfixExpression) {
+    v.visit_expression(&expr.left);
+    v.visit_expression(&expr.right);
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(v: &mut V, expr: &IfExpression) {
+    v.visit_expression(&expr.condition);
+    v.visit_block_statement(&expr.consequence);
+    if let Some(alternative) = &expr.alternative {
+        v.visit_block_statement(alternative);
+    }
+}
+
+pub fn walk_function_literal<V: Visitor + ?Sized>(v: &mut V, expr: &FunctionLiteral) {
+    for parameter in &expr.parameters {
+        v.visit_identifier(parameter);
+    }
+    v.visit_block_statement(&expr.body);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(v: &mut V, expr: &CallExpression) {
+    v.visit_expression(&expr.function);
+    for argument in &expr.arguments {
+        v.visit_expression(argument);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::evaluator_utils::environment::Environment;
+use crate::evaluator_utils::evaluator::eval;
+use crate::evaluator_utils::object::Object;
+use crate::lexer_utils::lexer::Lexer;
+use crate::parser_utils::dump::to_sexp;
+use crate::parser_utils::parser::Parser;
+use crate::repl_utils::session::{print_parse_errors, Session};
+use std::fs;
+use std::io::{self, Write};
+
+pub const NAMES: &[&str] = &[
+    ":env", ":ast", ":tokens", ":type", ":load", ":save", ":restore", ":reset", ":help",
+];
+
+pub const HELP: &str = "\
+:env            list the bindings in the session
+:ast <expr>     print the syntax tree of <expr>
+:tokens <expr>  print the tokens of <expr>
+:type <expr>    print the type of the value of <expr>
+:load <file>    evaluate a file into the session
+:save <file>    write the entries that built the session to a file
+:restore <file> replace the session with one rebuilt from a saved file
+:reset          clear the session, keeping the prelude
+:help           show this list
+q!              leave the REPL";
+
+// Whether a line of REPL input is a colon command rather than Crab code.
+pub fn is_command(input: &str) -> bool {
+    input.trim_start().starts_with(':')
+}
+
+// Runs a colon command against the session, writing its output to `out`.
+pub fn run(input: &str, session: &mut Session, out: &mut impl Write) -> io::Result<()> {
+    let input = input.trim();
+    let (command, argument) = match input.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (input, ""),
+    };
+    match (command, argument) {
+        (":env", "") => print_env(&session.env, out),
+        (":ast", expr) if !expr.is_empty() => print_ast(expr, out),
+        (":tokens", expr) if !expr.is_empty() => print_tokens(expr, out),
+        (":type", expr) if !expr.is_empty() => print_type(expr, &session.env, out),
+        (":load", path) if !path.is_empty() => load(path, session, out),
+        (":save", path) if !path.is_empty() => match session.save(path) {
+            Ok(()) => writeln!(
+                out,
+                "Saved {} entries to {}",
+                session.transcript.len(),
+                path
+            ),
+            Err(e) => writeln!(out, "Cannot write {}: {}", path, e),
+        },
+        (":restore", path) if !path.is_empty() => match session.restore(path) {
+            Ok(()) => writeln!(out, "Restored {}", path),
+            Err(e) => writeln!(out, "{}", e),
+        },
+        (":reset", "") => {
+            session.reset();
+            Ok(())
+        }
+        (":help", "") => writeln!(out, "{}", HELP),
+        (":env" | ":reset" | ":help", _) => writeln!(out, "{} takes no arguments", command),
+        (":ast" | ":tokens" | ":type" | ":load" | ":save" | ":restore", _) => {
+            writeln!(out, "{} needs an argument, see :help", command)
+        }
+        _ => writeln!(out, "Unknown command {}, see :help", command),
+    }
+}
+
+fn print_env(env: &Environment, out: &mut impl Write) -> io::Result<()> {
+    let bindings = env.bindings();
+    if bindings.is_empty() {
+        return writeln!(out, "(no bindings)");
+    }
+    for (name, value) in bindings {
+        writeln!(
+            out,
+            "{}: {:?} = {}",
+            name,
+            value.object_type(),
+            summary(value)
+        )?;
+    }
+    Ok(())
+}
+
+// A one-line rendering of a value; functions show only their parameters.
+fn summary(value: &Object) -> String {
+    match value {
+        Object::Function(f) => format!("fn({})", f.parameters.join(", ")),
+        value => value.inspect(),
+    }
+}
+
+fn print_ast(expr: &str, out: &mut impl Write) -> io::Result<()> {
+    let mut p = Parser::new(Lexer::new(expr.to_string()));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return print_parse_errors(p.errors(), out);
+    }
+    write!(out, "{}", to_sexp(&program))
+}
+
+fn print_tokens(expr: &str, out: &mut impl Write) -> io::Result<()> {
+    for tok in Lexer::new(expr.to_string()) {
+        writeln!(
+            out,
+            "{}\t{:?}\t{:?}",
+            tok.position, tok.token_type, tok.literal
+        )?;
+    }
+    Ok(())
+}
+
+// Evaluates in a copy of the session, so `:type let x = 1` binds nothing.
+fn print_type(expr: &str, env: &Environment, out: &mut impl Write) -> io::Result<()> {
+    let mut p = Parser::new(Lexer::new(expr.to_string()));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        return print_parse_errors(p.errors(), out);
+    }
+    let evaluated = eval(&program.statements, &mut env.clone());
+    writeln!(out, "{:?}", evaluated.object_type())
+}
+
+fn load(path: &str, session: &mut Session, out: &mut impl Write) -> io::Result<()> {
+    match fs::read_to_string(path) {
+        Ok(input) => session.evaluate(input, out),
+        Err(e) => writeln!(out, "Cannot read {}: {}", path, e),
+    }
+}
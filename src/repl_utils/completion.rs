@@ -0,0 +1,64 @@
+use crate::evaluator_utils::builtins;
+use crate::evaluator_utils::environment::Environment;
+use crate::lexer_utils::token::Keywords;
+use crate::repl_utils::commands;
+use unicode_xid::UnicodeXID;
+
+// Completes the word before the cursor from the keywords, the builtins and
+// the names bound in the session. `names` is refreshed by the REPL after
+// every entry.
+pub struct CrabHelper {
+    pub names: Vec<String>,
+}
+impl CrabHelper {
+    pub fn new() -> CrabHelper {
+        CrabHelper { names: Vec::new() }
+    }
+
+    pub fn update(&mut self, env: &Environment) {
+        self.names = env.names().into_iter().map(String::from).collect();
+    }
+
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        complete(line, &self.names)
+    }
+}
+
+impl Default for CrabHelper {
+    fn default() -> CrabHelper {
+        CrabHelper::new()
+    }
+}
+
+// Returns where the word before the end of `line` starts and the sorted
+// completions for it. A `:` at the start of the line completes commands.
+pub fn complete(line: &str, names: &[String]) -> (usize, Vec<String>) {
+    if line.starts_with(':') && !line.contains(char::is_whitespace) {
+        return (0, matching(line, commands::NAMES.iter().copied()));
+    }
+    let start = line
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_xid_continue())
+        .last()
+        .map_or(line.len(), |(i, _)| i);
+    let word = &line[start..];
+    if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+        return (start, Vec::new());
+    }
+    let mut candidates: Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+    for name in Keywords::new().map.into_keys().chain(builtins::names()) {
+        candidates.push(name);
+    }
+    (start, matching(word, candidates.into_iter()))
+}
+
+fn matching<'a>(prefix: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut matches: Vec<String> = candidates
+        .filter(|c| c.starts_with(prefix))
+        .map(String::from)
+        .collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
@@ -0,0 +1,431 @@
+use crate::repl_utils::completion::CrabHelper;
+use crate::repl_utils::history::History;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::process::{Command, Stdio};
+
+// Why `Editor::readline` returned without a line.
+#[derive(Debug)]
+pub enum ReadError {
+    Interrupted, // Ctrl-C
+    Eof,         // Ctrl-D on an empty line, or the end of the input
+    Io(io::Error),
+}
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Interrupted => write!(f, "interrupted"),
+            ReadError::Eof => write!(f, "end of input"),
+            ReadError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> ReadError {
+        ReadError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    Unknown,
+}
+
+// Reads lines from the terminal with emacs-style editing keys, history and
+// completion. When stdin is not a terminal, lines are read as they are.
+pub struct Editor {
+    pub history: History,
+    pub helper: CrabHelper,
+}
+impl Editor {
+    pub fn new(history_size: usize) -> Editor {
+        Editor {
+            history: History::new(history_size),
+            helper: CrabHelper::new(),
+        }
+    }
+
+    pub fn readline(&mut self, prompt: &str) -> Result<String, ReadError> {
+        let stdin = io::stdin();
+        let _raw = match RawMode::enable() {
+            Ok(raw) => raw,
+            Err(_) => {
+                let mut line = String::new();
+                if stdin.lock().read_line(&mut line)? == 0 {
+                    return Err(ReadError::Eof);
+                }
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                return Ok(line);
+            }
+        };
+        let width = terminal_width();
+        let mut keys = stdin.lock().bytes();
+        self.edit(prompt, &mut keys, &mut io::stdout(), width)
+    }
+
+    // Edits a line read key by key from `keys`, drawing it on `out`, a
+    // terminal `width` columns wide, until Enter.
+    pub fn edit(
+        &mut self,
+        prompt: &str,
+        keys: &mut impl Iterator<Item = io::Result<u8>>,
+        out: &mut impl Write,
+        width: usize,
+    ) -> Result<String, ReadError> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // Position in the history; `entries.len()` is the line being typed,
+        // which `typed` keeps while earlier entries are shown.
+        let mut browsing = self.history.entries.len();
+        let mut typed = Vec::new();
+        let mut pending = None;
+        loop {
+            refresh(out, prompt, &line, cursor, width)?;
+            let key = match pending.take() {
+                Some(key) => key,
+                None => read_key(keys)?,
+            };
+            match key {
+                Key::Enter => {
+                    writeln!(out)?;
+                    out.flush()?;
+                    return Ok(line.into_iter().collect());
+                }
+                Key::Ctrl('c') => {
+                    writeln!(out, "^C")?;
+                    out.flush()?;
+                    return Err(ReadError::Interrupted);
+                }
+                Key::Ctrl('d') if line.is_empty() => {
+                    writeln!(out)?;
+                    out.flush()?;
+                    return Err(ReadError::Eof);
+                }
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace | Key::Ctrl('h') if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete | Key::Ctrl('d') if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left | Key::Ctrl('b') => cursor = cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => cursor = (cursor + 1).min(line.len()),
+                Key::Home | Key::Ctrl('a') => cursor = 0,
+                Key::End | Key::Ctrl('e') => cursor = line.len(),
+                Key::WordLeft => cursor = word_start(&line, cursor),
+                Key::WordRight => cursor = word_end(&line, cursor),
+                Key::Ctrl('k') => line.truncate(cursor),
+                Key::Ctrl('u') => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::Ctrl('w') => {
+                    let start = word_start(&line, cursor);
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Ctrl('l') => write!(out, "\x1b[H\x1b[2J")?,
+                Key::Up | Key::Ctrl('p') if browsing > 0 => {
+                    if browsing == self.history.entries.len() {
+                        typed = line;
+                    }
+                    browsing -= 1;
+                    line = self.history.entries[browsing].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down | Key::Ctrl('n') if browsing < self.history.entries.len() => {
+                    browsing += 1;
+                    line = match self.history.entries.get(browsing) {
+                        Some(entry) => entry.chars().collect(),
+                        None => typed.clone(),
+                    };
+                    cursor = line.len();
+                }
+                Key::Ctrl('r') => {
+                    let (found, next) = self.search(keys, out, width)?;
+                    if let Some(i) = found {
+                        browsing = i;
+                        line = self.history.entries[i].chars().collect();
+                        cursor = line.len();
+                    }
+                    pending = next;
+                }
+                Key::Tab => cursor = self.complete(out, &mut line, cursor)?,
+                _ => write!(out, "\x07")?,
+            }
+        }
+    }
+
+    // Searches the history backwards for what is typed after Ctrl-R; Ctrl-R
+    // again finds an older match. Returns the history index of the match to
+    // use, `None` if cancelled with Ctrl-G, and the key that ended the search
+    // to be handled as usual.
+    fn search(
+        &self,
+        keys: &mut impl Iterator<Item = io::Result<u8>>,
+        out: &mut impl Write,
+        width: usize,
+    ) -> Result<(Option<usize>, Option<Key>), ReadError> {
+        let entries = &self.history.entries;
+        let find =
+            |query: &str, before: usize| (0..before).rev().find(|&i| entries[i].contains(query));
+        let mut query = String::new();
+        let mut found = None;
+        let mut failed = false;
+        loop {
+            let prompt = format!(
+                "({}reverse-i-search)`{}': ",
+                if failed { "failed " } else { "" },
+                query
+            );
+            let shown: Vec<char> = found
+                .map_or("", |i: usize| entries[i].as_str())
+                .chars()
+                .collect();
+            refresh(out, &prompt, &shown, shown.len(), width)?;
+            let key = read_key(keys)?;
+            let before = match key {
+                Key::Char(c) => {
+                    query.push(c);
+                    found.map_or(entries.len(), |i| i + 1)
+                }
+                Key::Backspace | Key::Ctrl('h') => {
+                    query.pop();
+                    entries.len()
+                }
+                Key::Ctrl('r') => found.unwrap_or(entries.len()),
+                Key::Ctrl('g') => return Ok((None, None)),
+                key => return Ok((found, Some(key))),
+            };
+            match find(&query, before) {
+                Some(i) => {
+                    found = Some(i);
+                    failed = false;
+                }
+                None => failed = true,
+            }
+        }
+    }
+
+    // Completes the word before `cursor` as far as all candidates agree, or
+    // lists them below the line when that adds nothing. Returns the new
+    // cursor.
+    fn complete(
+        &self,
+        out: &mut impl Write,
+        line: &mut Vec<char>,
+        cursor: usize,
+    ) -> io::Result<usize> {
+        let before: String = line[..cursor].iter().collect();
+        let (start, candidates) = self.helper.complete(&before);
+        let start = before[..start].chars().count();
+        let prefix: Vec<char> = common_prefix(&candidates).chars().collect();
+        if prefix.len() > cursor - start {
+            line.splice(start..cursor, prefix.iter().copied());
+            return Ok(start + prefix.len());
+        }
+        if candidates.len() > 1 {
+            write!(out, "\n{}\n", candidates.join("  "))?;
+        } else {
+            write!(out, "\x07")?;
+        }
+        Ok(cursor)
+    }
+}
+
+fn read_key(keys: &mut impl Iterator<Item = io::Result<u8>>) -> Result<Key, ReadError> {
+    let mut next = || {
+        keys.next()
+            .unwrap_or(Err(io::ErrorKind::UnexpectedEof.into()))
+    };
+    let byte = match next() {
+        Ok(byte) => byte,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(ReadError::Eof),
+        Err(e) => return Err(ReadError::Io(e)),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f => Key::Backspace,
+        0x1b => match next()? {
+            b'[' => {
+                // Parameters, then a final byte like `A` or `~`.
+                let mut params = String::new();
+                let last = loop {
+                    match next()? {
+                        b @ (b'0'..=b'9' | b';') => params.push(b as char),
+                        b => break b,
+                    }
+                };
+                match (params.as_str(), last) {
+                    ("", b'A') => Key::Up,
+                    ("", b'B') => Key::Down,
+                    ("", b'C') => Key::Right,
+                    ("", b'D') => Key::Left,
+                    ("", b'H') | ("1" | "7", b'~') => Key::Home,
+                    ("", b'F') | ("4" | "8", b'~') => Key::End,
+                    ("3", b'~') => Key::Delete,
+                    ("1;5" | "1;3", b'C') => Key::WordRight,
+                    ("1;5" | "1;3", b'D') => Key::WordLeft,
+                    _ => Key::Unknown,
+                }
+            }
+            b'O' => match next()? {
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                _ => Key::Unknown,
+            },
+            b'b' => Key::WordLeft,
+            b'f' => Key::WordRight,
+            _ => Key::Unknown,
+        },
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        0..=0x1f => Key::Unknown,
+        0x80.. => {
+            // The leading byte of a UTF-8 sequence gives its length.
+            let len = byte.leading_ones() as usize;
+            let mut bytes = vec![byte];
+            for _ in 1..len.clamp(1, 4) {
+                bytes.push(next()?);
+            }
+            match std::str::from_utf8(&bytes) {
+                Ok(s) => Key::Char(s.chars().next().unwrap()),
+                Err(_) => Key::Unknown,
+            }
+        }
+        _ => Key::Char(byte as char),
+    };
+    Ok(key)
+}
+
+// Redraws the prompt and line, scrolling the line sideways when it doesn't
+// fit so the cursor stays in view. Newlines in entries recalled from the
+// history show as `↵`.
+fn refresh(
+    out: &mut impl Write,
+    prompt: &str,
+    line: &[char],
+    cursor: usize,
+    width: usize,
+) -> io::Result<()> {
+    let prompt_width = prompt.chars().count();
+    let room = width.saturating_sub(prompt_width + 1).max(1);
+    let start = cursor.saturating_sub(room);
+    let end = line.len().min(start + room);
+    let shown: String = line[start..end]
+        .iter()
+        .map(|&c| if c == '\n' { '↵' } else { c })
+        .collect();
+    write!(out, "\r{}{}\x1b[K\r", prompt, shown)?;
+    let column = prompt_width + cursor - start;
+    if column > 0 {
+        write!(out, "\x1b[{}C", column)?;
+    }
+    out.flush()
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn word_start(line: &[char], cursor: usize) -> usize {
+    let mut i = cursor;
+    while i > 0 && !is_word(line[i - 1]) {
+        i -= 1;
+    }
+    while i > 0 && is_word(line[i - 1]) {
+        i -= 1;
+    }
+    i
+}
+
+fn word_end(line: &[char], cursor: usize) -> usize {
+    let mut i = cursor;
+    while i < line.len() && !is_word(line[i]) {
+        i += 1;
+    }
+    while i < line.len() && is_word(line[i]) {
+        i += 1;
+    }
+    i
+}
+
+fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut prefix = first.as_str();
+    for candidate in &candidates[1..] {
+        while !candidate.starts_with(prefix) {
+            prefix = &prefix[..prefix.char_indices().last().map_or(0, |(i, _)| i)];
+        }
+    }
+    prefix
+}
+
+// Puts the terminal in a mode where keys arrive one at a time and aren't
+// echoed, and restores the old mode when dropped. Uses `stty`, so it fails
+// where there is no terminal or no `stty`.
+struct RawMode {
+    saved: String,
+}
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&[
+            "-icanon", "-echo", "-isig", "-ixon", "-iexten", "min", "1", "time", "0",
+        ])?;
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Columns of the terminal, or 80 when `stty` can't tell.
+fn terminal_width() -> usize {
+    stty(&["size"])
+        .ok()
+        .and_then(|size| size.split_whitespace().nth(1)?.parse().ok())
+        .filter(|&width| width > 0)
+        .unwrap_or(80)
+}
@@ -0,0 +1,102 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+// Earlier entries, oldest first. Entries are kept in a file one per line,
+// with newlines and backslashes escaped, after a `#V2` header line.
+pub struct History {
+    pub entries: Vec<String>,
+    max_size: usize,
+    unsaved: usize, // entries at the end not yet appended to the file
+    saved: usize,   // entries the file holds
+}
+impl History {
+    pub fn new(max_size: usize) -> History {
+        History {
+            entries: Vec::new(),
+            max_size,
+            unsaved: 0,
+            saved: 0,
+        }
+    }
+
+    // Adds `entry` unless it is blank or repeats the latest one.
+    pub fn add(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        self.unsaved += 1;
+        if self.entries.len() > self.max_size {
+            self.entries.remove(0);
+            self.unsaved = self.unsaved.min(self.max_size);
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines().peekable();
+        if lines.peek() == Some(&HEADER) {
+            lines.next();
+        }
+        let mut count = 0;
+        for line in lines {
+            self.add(&unescape(line));
+            count += 1;
+        }
+        self.unsaved = 0;
+        self.saved = count;
+        Ok(())
+    }
+
+    // Appends the entries added since the last load or save to the file at
+    // `path`, rewriting it instead once it holds more than `max_size`.
+    pub fn save(&mut self, path: &Path) -> io::Result<()> {
+        if self.saved + self.unsaved > self.max_size {
+            let mut contents = format!("{}\n", HEADER);
+            for entry in &self.entries {
+                contents.push_str(&format!("{}\n", escape(entry)));
+            }
+            fs::write(path, contents)?;
+            self.saved = self.entries.len();
+        } else {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            let mut contents = String::new();
+            if file.metadata()?.len() == 0 {
+                contents.push_str(&format!("{}\n", HEADER));
+            }
+            for entry in &self.entries[self.entries.len() - self.unsaved..] {
+                contents.push_str(&format!("{}\n", escape(entry)));
+            }
+            file.write_all(contents.as_bytes())?;
+            self.saved += self.unsaved;
+        }
+        self.unsaved = 0;
+        Ok(())
+    }
+}
+
+const HEADER: &str = "#V2";
+
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            (c, _) => entry.push(c),
+        }
+    }
+    entry
+}
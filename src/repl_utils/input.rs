@@ -0,0 +1,51 @@
+use crate::lexer_utils::lexer::Lexer;
+use crate::lexer_utils::token::TokenType;
+use crate::repl_utils::commands;
+
+// Whether `line`, the first line of an input, is `q!` or a `:` command. These
+// are never continued, even when they end in an operator like `:type x +`.
+pub fn is_single_line(line: &str) -> bool {
+    line.trim() == "q!" || commands::is_command(line)
+}
+
+// Whether `input` can be handed to the parser, or the REPL should keep reading
+// lines. Input is incomplete while a `(`, `{` or `[` is left open, or when it
+// ends in an operator, a comma or a keyword that needs something after it.
+// Stray closing delimiters count as complete so the parser can report them.
+pub fn is_complete(input: &str) -> bool {
+    let mut depth: isize = 0;
+    let mut last = None;
+    for tok in Lexer::new(input.to_string()) {
+        match (&tok.token_type, tok.literal.as_str()) {
+            (TokenType::LPAREN | TokenType::LBRACE, _) | (TokenType::ILLEGAL, "[") => depth += 1,
+            (TokenType::RPAREN | TokenType::RBRACE, _) | (TokenType::ILLEGAL, "]") => depth -= 1,
+            (TokenType::EOF, _) => break,
+            _ => {}
+        }
+        last = Some(tok.token_type);
+    }
+    if depth > 0 {
+        return false;
+    }
+    !matches!(
+        last,
+        Some(
+            TokenType::ASSIGN
+                | TokenType::PLUS
+                | TokenType::MINUS
+                | TokenType::ASTERISK
+                | TokenType::SLASH
+                | TokenType::BANG
+                | TokenType::LT
+                | TokenType::GT
+                | TokenType::EQ
+                | TokenType::NOTEQ
+                | TokenType::COMMA
+                | TokenType::LET
+                | TokenType::RETURN
+                | TokenType::IF
+                | TokenType::ELSE
+                | TokenType::FUNCTION
+        )
+    )
+}
@@ -0,0 +1,10 @@
+pub mod commands;
+pub mod completion;
+pub mod editor;
+pub mod history;
+pub mod input;
+pub mod repl;
+pub mod session;
+pub mod transcript;
+#[cfg(test)]
+pub mod tests;
@@ -0,0 +1,170 @@
+use crate::evaluator_utils::evaluator::eval;
+use crate::evaluator_utils::object::Object;
+use crate::parser_utils::parser::Parser;
+use crate::repl_utils::commands;
+use crate::repl_utils::editor::{Editor, ReadError};
+use crate::repl_utils::input::{is_complete, is_single_line};
+use crate::repl_utils::session::Session;
+use crate::{evaluator_utils::environment::Environment, lexer_utils::lexer::Lexer};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+const HISTORY_SIZE: usize = 1000;
+
+// Starts an interactive session. `rc` is a file given on the command line to
+// evaluate before the first prompt, in place of `$CRABRC` or `~/.crabrc`.
+pub fn start(rc: Option<&str>) {
+    println!("q! for exit, :help for commands.");
+    let mut session = Session::new();
+    if let Some((path, required)) = rc_path(rc) {
+        load_rc(&path, required, &mut session.env, &mut io::stderr())
+            .expect("error: unable to write output");
+    }
+    let mut editor = Editor::new(HISTORY_SIZE);
+    let mut history = history_path();
+    if let Some(path) = &history {
+        // A missing file just means this is the first session.
+        let _ = editor.history.load(path);
+    }
+    let mut stdout = io::stdout();
+    loop {
+        editor.helper.update(&session.env);
+        let input = match read_input(|prompt| editor.readline(prompt)) {
+            Some(input) => input,
+            None => break,
+        };
+        if !input.trim().is_empty() {
+            editor.history.add(input.trim_end());
+            if let Some(path) = &history {
+                if !save_history(&mut editor, path) {
+                    history = None;
+                }
+            }
+        }
+        if input.trim() == "q!" {
+            break;
+        }
+        let result = if commands::is_command(&input) {
+            commands::run(&input, &mut session, &mut stdout)
+        } else {
+            session.evaluate(input, &mut stdout)
+        };
+        result.expect("error: unable to write output");
+    }
+}
+
+// The rc file to load, and whether it must exist: the one given on the
+// command line or in `$CRABRC`, otherwise `~/.crabrc` if there is one.
+fn rc_path(flag: Option<&str>) -> Option<(PathBuf, bool)> {
+    if let Some(path) = flag {
+        return Some((PathBuf::from(path), true));
+    }
+    if let Some(path) = env::var_os("CRABRC").filter(|path| !path.is_empty()) {
+        return Some((PathBuf::from(path), true));
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some((PathBuf::from(home).join(".crabrc"), false))
+}
+
+// Evaluates an rc file into the session. Errors are reported to `err` and
+// the session starts anyway; a missing file is only an error if `required`.
+pub fn load_rc(
+    path: &Path,
+    required: bool,
+    env: &mut Environment,
+    err: &mut impl Write,
+) -> io::Result<()> {
+    let name = path.display();
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => return Ok(()),
+        Err(e) => return writeln!(err, "{}: unable to read: {}", name, e),
+    };
+    let mut p = Parser::new(Lexer::new(input));
+    let program = p.parse_program();
+    if !p.errors().is_empty() {
+        for e in p.errors() {
+            writeln!(err, "{}:{}", name, e)?;
+        }
+        return Ok(());
+    }
+    if let Object::Error(e) = eval(&program.statements, env) {
+        writeln!(err, "{}: runtime error: {}", name, e.message)?;
+    }
+    Ok(())
+}
+
+// Reads lines with `read_line` until they form a complete input, showing the
+// continuation prompt while they don't. `q!` and commands are always one line.
+// A blank line submits whatever was typed so far and Ctrl-C discards it.
+// Returns `None` at the end of input (Ctrl-D).
+pub fn read_input(mut read_line: impl FnMut(&str) -> Result<String, ReadError>) -> Option<String> {
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        let line = match read_line(prompt) {
+            Ok(line) => line,
+            Err(ReadError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadError::Eof) => return None,
+            Err(e) => {
+                eprintln!("error: unable to read input: {}", e);
+                return None;
+            }
+        };
+        let blank = line.trim().is_empty();
+        let single = input.is_empty() && is_single_line(&line);
+        input.push_str(&line);
+        input.push('\n');
+        if single || blank || is_complete(&input) {
+            return Some(input);
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    history_path_from(|name| env::var_os(name))
+}
+
+// Where the history is kept between sessions: `crab/history` under
+// `$XDG_CONFIG_HOME`, `%APPDATA%` or `~/.config`, whichever is set first.
+// `var` looks up environment variables.
+pub fn history_path_from(var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let config_dir = var("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+        .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("crab").join("history"))
+}
+
+// Appends the new entries to the history file. History is a convenience, so
+// failing to write it only earns a warning, and the caller stops trying.
+fn save_history(editor: &mut Editor, path: &Path) -> bool {
+    let result = match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    };
+    match result.and_then(|_| editor.history.save(path)) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!(
+                "warning: unable to save history to {}: {}",
+                path.display(),
+                e
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::evaluator_utils::environment::Environment;
+use crate::evaluator_utils::evaluator::eval;
+use crate::evaluator_utils::object::{Null, Object};
+use crate::evaluator_utils::prelude;
+use crate::evaluator_utils::runtime::Runtime;
+use crate::lexer_utils::lexer::Lexer;
+use crate::lexer_utils::token::{Position, TokenType};
+use crate::parser_utils::ast::{Node, Statement};
+use crate::parser_utils::parser::Parser;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+// The state of a REPL session: its bindings, and the transcript of entries
+// that built them. Of an entry that fails, only the statements before the
+// failing one are recorded, as only they ran. Replaying the transcript in a
+// fresh session brings every value and function back.
+pub struct Session {
+    pub env: Environment,
+    pub transcript: Vec<String>,
+}
+impl Session {
+    pub fn new() -> Session {
+        Session::with_runtime(Arc::new(Runtime::new()))
+    }
+
+    pub fn with_runtime(runtime: Arc<Runtime>) -> Session {
+        Session {
+            env: prelude::environment_with(runtime),
+            transcript: Vec::new(),
+        }
+    }
+
+    // Clears the bindings and the transcript. The runtime, and so where the
+    // session prints, stays the same.
+    pub fn reset(&mut self) {
+        *self = Session::with_runtime(self.env.runtime().clone());
+    }
+
+    // Parses and evaluates one entry, printing its value or the parse errors.
+    pub fn evaluate(&mut self, input: String, out: &mut impl Write) -> io::Result<()> {
+        if input.trim().is_empty() {
+            return Ok(());
+        }
+        let l = Lexer::new(input.clone());
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+
+        if !p.errors().is_empty() {
+            return print_parse_errors(p.errors(), out);
+        }
+        self.env.runtime().reset();
+        // One statement at a time, to know which ones ran.
+        let mut evaluated = Object::Null(Null {});
+        let mut failed = None;
+        for stmt in &program.statements {
+            evaluated = eval(&vec![stmt.clone()], &mut self.env);
+            if matches!(evaluated, Object::Error(_)) {
+                failed = Some(stmt.get_token().position);
+                break;
+            }
+            if matches!(stmt, Node::Statement(Statement::ReturnStatement(_))) {
+                break;
+            }
+        }
+        let ran = match failed {
+            Some(position) => &input[..offset(&input, position)],
+            None => &input,
+        };
+        if !ran.trim().is_empty() {
+            self.transcript.push(terminated(ran));
+        }
+        writeln!(out, "{}", evaluated.inspect())
+    }
+
+    // Writes the transcript to `path` as a Crab program.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut source = String::new();
+        for entry in &self.transcript {
+            source.push_str(entry);
+            source.push('\n');
+        }
+        fs::write(path, source)
+    }
+
+    // Replaces the session with one built by evaluating the program at
+    // `path`, as written by `save`. Returns the error to report if it can't
+    // be read, parsed or evaluated, leaving the session as it was.
+    pub fn restore(&mut self, path: &str) -> Result<(), String> {
+        let input = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let mut p = Parser::new(Lexer::new(input.clone()));
+        let program = p.parse_program();
+        if let Some(e) = p.errors().first() {
+            return Err(format!("{}:{}", path, e));
+        }
+        let mut session = Session::with_runtime(self.env.runtime().clone());
+        if let Object::Error(e) = eval(&program.statements, &mut session.env) {
+            return Err(format!("{}: runtime error: {}", path, e.message));
+        }
+        session.transcript.push(terminated(&input));
+        *self = session;
+        Ok(())
+    }
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session::new()
+    }
+}
+
+pub fn print_parse_errors(errors: &[String], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "Parser Errors:")?;
+    for e in errors {
+        writeln!(out, "\t{}", e)?;
+    }
+    Ok(())
+}
+
+// Byte offset of `position` in `input`.
+fn offset(input: &str, position: Position) -> usize {
+    let line_start: usize = input
+        .split_inclusive('\n')
+        .take(position.line - 1)
+        .map(str::len)
+        .sum();
+    let column = input[line_start..]
+        .char_indices()
+        .nth(position.column - 1)
+        .map_or(input.len() - line_start, |(i, _)| i);
+    line_start + column
+}
+
+// Ends an entry with `;` so that entries written one after another can't run
+// together, as `f` followed by `(1)` would. A trailing comment pushes the `;`
+// onto a line of its own.
+fn terminated(entry: &str) -> String {
+    let entry = entry.trim_end();
+    let mut l = Lexer::new(entry.to_string());
+    let last = l
+        .by_ref()
+        .filter(|tok| tok.token_type != TokenType::EOF)
+        .last();
+    match last {
+        Some(tok) if tok.token_type == TokenType::SEMICOLON => entry.to_string(),
+        Some(tok) if l.comments.last().is_some_and(|c| c.position > tok.position) => {
+            format!("{}\n;", entry)
+        }
+        _ => format!("{};", entry),
+    }
+}
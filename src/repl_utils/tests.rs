@@ -0,0 +1,504 @@
+use crate::evaluator_utils::environment::Environment;
+use crate::evaluator_utils::object::{Null, Object};
+use crate::repl_utils::commands;
+use crate::repl_utils::completion::complete;
+use crate::repl_utils::editor::{Editor, ReadError};
+use crate::repl_utils::history::History;
+use crate::repl_utils::input::is_complete;
+use crate::repl_utils::repl::{history_path_from, load_rc, read_input};
+use crate::repl_utils::session::Session;
+use crate::repl_utils::transcript;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+// Feeds each line to the REPL in one session and returns what it printed.
+fn run_session(lines: &[&str]) -> String {
+    let mut session = Session::new();
+    let mut out = Vec::new();
+    for line in lines {
+        if commands::is_command(line) {
+            commands::run(line, &mut session, &mut out).unwrap();
+        } else {
+            session.evaluate(line.to_string(), &mut out).unwrap();
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_is_complete() {
+    let tests = [
+        ("", true),
+        ("5 + 5", true),
+        ("let x = 5;", true),
+        ("let add = fn(a, b) {", false),
+        ("let add = fn(a, b) {\n  a + b\n", false),
+        ("let add = fn(a, b) {\n  a + b\n};", true),
+        ("add(1,", false),
+        ("add(1,\n2)", true),
+        ("if (x) { 1 } else", false),
+        ("if (x) { 1 } else { 2 }", true),
+        ("1 +", false),
+        ("1 *\n", false),
+        ("x ==", false),
+        ("let x =", false),
+        ("return", false),
+        ("[1, 2", false),
+        ("[1, 2]", true),
+        ("{ // comment with (", false),
+        ("1 + 2 // trailing +", true),
+        ("}", true),
+        ("foo())", true),
+    ];
+
+    for (i, (input, expected)) in tests.iter().enumerate() {
+        assert_eq!(
+            is_complete(input),
+            *expected,
+            "Test [{}] - Completeness of {:?} is wrong",
+            i,
+            input
+        );
+    }
+}
+
+#[test]
+fn test_read_input() {
+    let tests: Vec<(Vec<&str>, Option<&str>)> = vec![
+        (vec!["1 + 1"], Some("1 + 1\n")),
+        (vec!["1 +", "1"], Some("1 +\n1\n")),
+        (
+            vec!["let f = fn(x) {", "", "1"],
+            Some("let f = fn(x) {\n\n"),
+        ),
+        (vec!["q!", "1 + 1"], Some("q!\n")),
+        (vec!["  q!  "], Some("  q!  \n")),
+        (vec![":type x +", "1"], Some(":type x +\n")),
+        (vec![":load /tmp/a-", "1"], Some(":load /tmp/a-\n")),
+        (vec!["1 +", "q!", ""], Some("1 +\nq!\n\n")),
+        (vec!["1 +"], None),
+    ];
+
+    for (i, (lines, expected)) in tests.iter().enumerate() {
+        let mut lines = lines.iter();
+        let input = read_input(|_| match lines.next() {
+            Some(line) => Ok(line.to_string()),
+            None => Err(ReadError::Eof),
+        });
+        assert_eq!(
+            input.as_deref(),
+            *expected,
+            "Test [{}] - Input read is wrong",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_editor() {
+    let tests: Vec<(&str, Result<&str, &str>)> = vec![
+        ("abc\r", Ok("abc")),
+        ("abc\x1b[D\x1b[DX\n", Ok("aXbc")),
+        ("abc\x01X\x05Y\r", Ok("XabcY")),
+        ("abc\x1b[H\x1b[3~\r", Ok("bc")),
+        ("abc\x7f\r", Ok("ab")),
+        ("let xy = 1\x17\x17\r", Ok("let ")),
+        ("abc\x1b[D\x1b[D\x0b\r", Ok("a")),
+        ("abc\x02\x15\r", Ok("c")),
+        ("a b\x1bbX\r", Ok("a Xb")),
+        ("é→\x1b[DX\r", Ok("éX→")),
+        ("\x1b[A\r", Ok("fn(a) {\n  a\n}")),
+        ("\x1b[A\x1b[A\r", Ok("x + 1")),
+        ("\x10\x10\x10\x10\x10\r", Ok("let x = 1;")),
+        ("typed\x1b[A\x1b[B\r", Ok("typed")),
+        ("typed\x1b[A\x1b[A\x1b[B\r", Ok("fn(a) {\n  a\n}")),
+        ("\x12x\r", Ok("x + 1")),
+        ("\x12x\x12\r", Ok("let x = 1;")),
+        ("\x12x =\x05!\r", Ok("let x = 1;!")),
+        ("\x12x\x12\x1b[B\r", Ok("x + 1")),
+        ("old\x12zz\x07\r", Ok("old")),
+        ("pu\t(1)\r", Ok("puts(1)")),
+        (":h\t\r", Ok(":help")),
+        ("ab\x01\x04\r", Ok("b")),
+        ("abc\x03", Err("interrupted")),
+        ("\x04", Err("end of input")),
+        ("abc", Err("end of input")),
+    ];
+
+    for (i, (keys, expected)) in tests.iter().enumerate() {
+        let mut editor = Editor::new(10);
+        for entry in ["let x = 1;", "x + 1", "fn(a) {\n  a\n}"] {
+            editor.history.add(entry);
+        }
+        let mut keys = keys.bytes().map(Ok);
+        let line = editor.edit(">> ", &mut keys, &mut Vec::new(), 80);
+        assert_eq!(
+            line.as_deref().map_err(|e| e.to_string()),
+            expected.map_err(String::from),
+            "Test [{}] - Line read is wrong",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_editor_lists_completions() {
+    let mut editor = Editor::new(10);
+    editor.helper.names = vec!["fooBar".to_string(), "fooBaz".to_string()];
+    let mut out = Vec::new();
+    let mut keys = "fo\t\t\r".bytes().map(Ok);
+    let line = editor.edit(">> ", &mut keys, &mut out, 80).unwrap();
+
+    assert_eq!(line, "fooBa", "Completed line is wrong");
+    let out = String::from_utf8(out).unwrap();
+    assert!(
+        out.contains("\nfooBar  fooBaz\n"),
+        "Completions are not listed in {:?}",
+        out
+    );
+}
+
+#[test]
+fn test_history() {
+    let path = std::env::temp_dir().join(format!("crab-history-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut history = History::new(3);
+    for entry in ["1", "1", " ", "let f = fn(x) {\n  x\n};", "a \\n b"] {
+        history.add(entry);
+    }
+    assert_eq!(
+        history.entries,
+        ["1", "let f = fn(x) {\n  x\n};", "a \\n b"],
+        "Entries are wrong"
+    );
+    history.save(&path).unwrap();
+    history.add("2");
+    history.save(&path).unwrap();
+
+    let mut loaded = History::new(3);
+    loaded.load(&path).unwrap();
+    assert_eq!(loaded.entries, history.entries, "Loaded entries are wrong");
+
+    // Past the size limit the file is rewritten with the latest entries.
+    loaded.add("3");
+    loaded.save(&path).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents, "#V2\na \\\\n b\n2\n3\n", "History file is wrong");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_commands() {
+    let path = std::env::temp_dir().join(format!("crab-load-{}.crb", std::process::id()));
+    std::fs::write(&path, "let loaded = 7;\nloaded * 2").unwrap();
+    let load = format!(":load {}", path.display());
+    let help = format!("{}\n", commands::HELP);
+
+    let tests: Vec<(Vec<&str>, &str)> = vec![
+        (vec![":env"], "(no bindings)\n"),
+        (
+            vec!["let x = 5;", "let f = fn(a, b) { a };", ":env"],
+            "5\nfn (a, b) {\na\n}\nf: Function = fn(a, b)\nx: Integer = 5\n",
+        ),
+        (vec![":ast -a * (b + 1)"], "(* (- a) (+ b 1))\n"),
+        (
+            vec![":tokens x+1"],
+            "1:1\tIDENT\t\"x\"\n1:2\tPLUS\t\"+\"\n1:3\tINT\t\"1\"\n1:4\tEOF\t\"\\0\"\n",
+        ),
+        (
+            vec![":type 1 < 2", ":type rational(1, 2)"],
+            "Boolean\nRational\n",
+        ),
+        (
+            vec![":type let y = 1;", "y"],
+            "Integer\nIdentifier not found: y\n",
+        ),
+        (
+            vec![":ast let = 1;"],
+            "Parser Errors:\n\t1:5: Expected next token to be IDENT, got ASSIGN instead\n",
+        ),
+        (vec![&load, "loaded"], "14\n7\n"),
+        (
+            vec![":load /nonexistent/file.crb"],
+            "Cannot read /nonexistent/file.crb: No such file or directory (os error 2)\n",
+        ),
+        (vec!["let x = 1;", ":reset", ":env"], "1\n(no bindings)\n"),
+        (vec![":help"], &help),
+        (vec![":type"], ":type needs an argument, see :help\n"),
+        (vec![":env x"], ":env takes no arguments\n"),
+        (vec![":nope"], "Unknown command :nope, see :help\n"),
+    ];
+
+    for (i, (lines, expected)) in tests.iter().enumerate() {
+        assert_eq!(
+            run_session(lines),
+            *expected,
+            "Test [{}] - Output of {:?} is wrong",
+            i,
+            lines
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_path() {
+    let tests = [
+        (
+            &[("XDG_CONFIG_HOME", "/tmp/config"), ("HOME", "/home/crab")][..],
+            Some("/tmp/config/crab/history"),
+        ),
+        (
+            &[("XDG_CONFIG_HOME", ""), ("HOME", "/home/crab")],
+            Some("/home/crab/.config/crab/history"),
+        ),
+        (
+            &[("APPDATA", "C:/AppData"), ("HOME", "/home/crab")],
+            Some("C:/AppData/crab/history"),
+        ),
+        (&[], None),
+    ];
+
+    for (i, (vars, expected)) in tests.iter().enumerate() {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| OsString::from(value))
+        };
+        assert_eq!(
+            history_path_from(var),
+            expected.map(PathBuf::from),
+            "Test [{}] - History path is wrong",
+            i
+        );
+    }
+}
+
+#[test]
+fn test_completion() {
+    let mut env = Environment::new();
+    env.set("rate".to_string(), Object::Null(Null {}));
+    let mut inner = Environment::new_enclosed(env);
+    inner.set("result".to_string(), Object::Null(Null {}));
+    let names: Vec<String> = inner.names().into_iter().map(String::from).collect();
+    assert_eq!(names, ["rate", "result"], "Environment names are wrong");
+
+    let tests = [
+        ("r", 0, vec!["rate", "rational", "result", "return"]),
+        ("let x = ra", 8, vec!["rate", "rational"]),
+        ("f(res", 2, vec!["result"]),
+        ("e", 0, vec!["else"]),
+        ("1 + ", 4, vec![]),
+        ("12", 0, vec![]),
+        ("zzz", 0, vec![]),
+        (":t", 0, vec![":tokens", ":type"]),
+        (":type re", 6, vec!["result", "return"]),
+    ];
+    for (i, (line, start, expected)) in tests.iter().enumerate() {
+        assert_eq!(
+            complete(line, &names),
+            (*start, expected.iter().map(|c| c.to_string()).collect()),
+            "Test [{}] - Completions for {:?} are wrong",
+            i,
+            line
+        );
+    }
+}
+
+#[test]
+fn test_load_rc() {
+    let dir = std::env::temp_dir();
+    let path = |name: &str| dir.join(format!("crab-rc-{}-{}", std::process::id(), name));
+    std::fs::write(path("ok"), "let twice = fn(x) { x * 2 };").unwrap();
+    std::fs::write(path("parse"), "let x = 1;\nlet = 2;").unwrap();
+    std::fs::write(path("runtime"), "let a = 1;\nlet b = a + true;\nlet c = 3;").unwrap();
+
+    let tests = [
+        ("ok", true, "", vec!["twice"]),
+        ("missing", false, "", vec![]),
+        (
+            "missing",
+            true,
+            ": unable to read: No such file or directory (os error 2)",
+            vec![],
+        ),
+        (
+            "parse",
+            true,
+            ":2:5: Expected next token to be IDENT, got ASSIGN instead",
+            vec![],
+        ),
+        (
+            "runtime",
+            true,
+            ": runtime error: Type mismatch: Integer + Boolean",
+            vec!["a"],
+        ),
+    ];
+
+    for (i, (name, required, error, bindings)) in tests.iter().enumerate() {
+        let mut env = Environment::new();
+        let mut err = Vec::new();
+        load_rc(&path(name), *required, &mut env, &mut err).unwrap();
+        let err = String::from_utf8(err).unwrap();
+        let expected = if error.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}\n", path(name).display(), error)
+        };
+        assert_eq!(err, expected, "Test [{}] - rc errors are wrong", i);
+        let names: Vec<&str> = env.bindings().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, *bindings, "Test [{}] - rc bindings are wrong", i);
+    }
+    for name in ["ok", "parse", "runtime"] {
+        std::fs::remove_file(path(name)).unwrap();
+    }
+}
+
+#[test]
+fn test_save_and_restore() {
+    let path = std::env::temp_dir().join(format!("crab-session-{}.crb", std::process::id()));
+    let path = path.display().to_string();
+    let save = format!(":save {}", path);
+    let restore = format!(":restore {}", path);
+
+    let output = run_session(&[
+        "let add = fn(a, b) { a + b }",
+        "let k = add(1, 2) // three",
+        "oops",
+        "let = 1;",
+        "if (k > 2) { k }",
+        "(10)",
+        &save,
+        ":reset",
+        &restore,
+        ":env",
+        "add(k, 10)",
+    ]);
+    assert_eq!(
+        output,
+        format!(
+            "fn (a, b) {{\n(a + b)\n}}\n3\nIdentifier not found: oops\nParser Errors:\n\t\
+             1:5: Expected next token to be IDENT, got ASSIGN instead\n3\n10\n\
+             Saved 4 entries to {0}\nRestored {0}\nadd: Function = fn(a, b)\nk: Integer = 3\n13\n",
+            path
+        ),
+        "Session output is wrong"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "let add = fn(a, b) { a + b };\nlet k = add(1, 2) // three\n;\nif (k > 2) { k };\n(10);\n",
+        "Saved transcript is wrong"
+    );
+
+    std::fs::write(&path, "let a = 1;\nlet b = a + true;").unwrap();
+    assert_eq!(
+        run_session(&["let kept = 1;", &restore, ":env"]),
+        format!(
+            "1\n{}: runtime error: Type mismatch: Integer + Boolean\nkept: Integer = 1\n",
+            path
+        ),
+        "Failed restore should keep the session"
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        run_session(&[&restore]),
+        format!(
+            "Cannot read {}: No such file or directory (os error 2)\n",
+            path
+        ),
+        "Missing file should be reported"
+    );
+}
+
+#[test]
+fn test_save_after_failed_entries() {
+    let path = std::env::temp_dir().join(format!("crab-partial-{}.crb", std::process::id()));
+    let path = path.display().to_string();
+    let save = format!(":save {}", path);
+    let restore = format!(":restore {}", path);
+
+    let output = run_session(&[
+        "let x = 1;\nlet y = x + true; let z = 3;",
+        "let w = 2; oops",
+        "missing",
+        &save,
+        ":reset",
+        &restore,
+        ":env",
+    ]);
+    assert_eq!(
+        output,
+        format!(
+            "Type mismatch: Integer + Boolean\nIdentifier not found: oops\n\
+             Identifier not found: missing\nSaved 2 entries to {0}\nRestored {0}\n\
+             w: Integer = 2\nx: Integer = 1\n",
+            path
+        ),
+        "Session output is wrong"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "let x = 1;\nlet w = 2;\n",
+        "Saved transcript is wrong"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_transcript_check_and_bless() {
+    let source =
+        "// header\n>> let x = 2;\n2\n\n// squares\n>> x *\n.. x\n5\n>> :type x\nInteger\n// end\n";
+    assert_eq!(
+        transcript::check("t.crbt", source),
+        Some(
+            "t.crbt:6: output differs for\n>> x *\n.. x\nexpected:\n    5\nactual:\n    4\n"
+                .to_string()
+        ),
+        "Transcript report is wrong"
+    );
+
+    let blessed = transcript::bless(source);
+    assert_eq!(
+        blessed,
+        source.replace("\n5\n", "\n4\n"),
+        "Blessed transcript is wrong"
+    );
+    assert_eq!(
+        transcript::check("t.crbt", &blessed),
+        None,
+        "Blessed transcript should pass"
+    );
+    assert_eq!(
+        transcript::bless(&blessed),
+        blessed,
+        "Blessing should be idempotent"
+    );
+}
+
+// Replays every transcript under `tests/transcripts`. To accept new output,
+// run `cargo run -- test --bless tests/transcripts/*.crbt` and review the diff.
+#[test]
+fn test_transcripts() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/transcripts");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "crbt"))
+        .collect();
+    paths.sort();
+    assert!(
+        !paths.is_empty(),
+        "No transcripts found in {}",
+        dir.display()
+    );
+
+    for path in paths {
+        let source = std::fs::read_to_string(&path).unwrap();
+        if let Some(report) = transcript::check(&path.display().to_string(), &source) {
+            panic!("{}", report);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// Golden tests written as REPL sessions. A `.crbt` file holds entries that
+// start with a `>> ` line, may continue on `.. ` lines, and are followed by
+// the output the REPL prints for them:
+//
+//     // Comments and blank lines between entries are kept as they are.
+//     >> let add = fn(a, b) {
+//     ..     a + b
+//     .. };
+//     fn (a, b) {
+//     (a + b)
+//     }
+//     >> add(1, 2)
+//     3
+//
+// All entries of a file run in one session, in order. Colon commands work as
+// they do in the REPL.
+
+use crate::evaluator_utils::runtime::{OutputBuffer, Runtime};
+use crate::repl_utils::commands;
+use crate::repl_utils::session::Session;
+use std::sync::Arc;
+
+const PROMPT: &str = ">>";
+const CONTINUATION_PROMPT: &str = "..";
+
+struct Entry {
+    leading: Vec<String>, // comments and blank lines before the entry
+    line: usize,          // line of the `>>` prompt
+    input: Vec<String>,   // the prompt lines as written
+    expected: Vec<String>,
+}
+
+struct Transcript {
+    entries: Vec<Entry>,
+    trailing: Vec<String>,
+}
+
+// Replays a transcript and returns a report of every entry whose output
+// differs from the expected one, or `None` if they all match.
+pub fn check(name: &str, source: &str) -> Option<String> {
+    let transcript = parse(source);
+    let actual = replay(&transcript);
+    let mut report = String::new();
+    for (entry, actual) in transcript.entries.iter().zip(&actual) {
+        if entry.expected != *actual {
+            report.push_str(&format!(
+                "{}:{}: output differs for\n{}\nexpected:\n{}\nactual:\n{}\n",
+                name,
+                entry.line,
+                entry.input.join("\n"),
+                indent(&entry.expected),
+                indent(actual),
+            ));
+        }
+    }
+    if report.is_empty() {
+        None
+    } else {
+        Some(report)
+    }
+}
+
+// Replays a transcript and returns it with the expected output of every entry
+// replaced by the actual one.
+pub fn bless(source: &str) -> String {
+    let transcript = parse(source);
+    let actual = replay(&transcript);
+    let mut out = String::new();
+    for (entry, actual) in transcript.entries.iter().zip(actual) {
+        for line in entry.leading.iter().chain(&entry.input).chain(&actual) {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    for line in &transcript.trailing {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn parse(source: &str) -> Transcript {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut pending = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim_end().to_string();
+        if is_prompt(&line, PROMPT) {
+            if let Some(entry) = entries.last_mut() {
+                take_filler(entry, &mut pending);
+            }
+            entries.push(Entry {
+                leading: std::mem::take(&mut pending),
+                line: i + 1,
+                input: vec![line],
+                expected: Vec::new(),
+            });
+            continue;
+        }
+        match entries.last_mut() {
+            Some(entry) if entry.expected.is_empty() && is_prompt(&line, CONTINUATION_PROMPT) => {
+                entry.input.push(line)
+            }
+            Some(entry) => entry.expected.push(line),
+            None => pending.push(line),
+        }
+    }
+    if let Some(entry) = entries.last_mut() {
+        take_filler(entry, &mut pending);
+    }
+    Transcript {
+        entries,
+        trailing: pending,
+    }
+}
+
+// Blank lines and comments at the end of an entry's output belong to
+// whatever comes next.
+fn take_filler(entry: &mut Entry, pending: &mut Vec<String>) {
+    while entry.expected.last().is_some_and(|l| is_filler(l)) {
+        pending.insert(0, entry.expected.pop().unwrap());
+    }
+}
+
+// Runs the entries in one session and returns what each printed: first the
+// lines written by `puts`, then the value, as they would appear in the REPL.
+fn replay(transcript: &Transcript) -> Vec<Vec<String>> {
+    let printed = OutputBuffer::new();
+    let runtime = Runtime::new();
+    runtime.set_output(Box::new(printed.clone()));
+    let mut session = Session::with_runtime(Arc::new(runtime));
+    let mut results = Vec::new();
+    for entry in &transcript.entries {
+        let input: Vec<&str> = entry.input.iter().map(|l| strip_prompt(l)).collect();
+        let input = input.join("\n");
+        let mut out = Vec::new();
+        if commands::is_command(&input) {
+            commands::run(&input, &mut session, &mut out).unwrap();
+        } else {
+            session.evaluate(input, &mut out).unwrap();
+        }
+        let out = printed.take() + &String::from_utf8_lossy(&out);
+        results.push(out.lines().map(|l| l.trim_end().to_string()).collect());
+    }
+    results
+}
+
+fn is_prompt(line: &str, prompt: &str) -> bool {
+    line == prompt || line.starts_with(&format!("{} ", prompt))
+}
+
+// `>> x` and `.. x` become `x`; a bare `>>` becomes an empty line.
+fn strip_prompt(line: &str) -> &str {
+    line.get(3..).unwrap_or("")
+}
+
+fn is_filler(line: &str) -> bool {
+    line.is_empty() || line.starts_with("//")
+}
+
+fn indent(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|l| format!("    {}", l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}